@@ -1,17 +1,888 @@
 use egui::{ColorImage, TextureOptions};
 use std::path::Path;
 
+use crate::atlas::{AtlasGrid, GridLayout, MAX_CARD_SIDE};
+
 #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
 use rfd::FileDialog;
 
 // A named rectangular region on a card (x,y,width,height in card pixel coords)
-#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Region {
     pub name: String,
     pub x: usize,
     pub y: usize,
     pub width: usize,
     pub height: usize,
+    // Overlay color (RGBA, unmultiplied); None uses the default region color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 4]>,
+    // Hidden regions are not drawn over the preview and cannot be clicked there
+    #[serde(default = "region_visible_default")]
+    pub visible: bool,
+    // Locked regions cannot be moved, resized, nudged or deleted with the selection
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    // Free-form categories ("text", "icon", "art"...) for filtering and grouping exports
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+fn region_visible_default() -> bool {
+    true
+}
+
+// Outline of regions without their own color
+const DEFAULT_REGION_COLOR: [u8; 4] = [200, 100, 100, 180];
+
+impl Region {
+    fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Width to height, e.g. "1.42:1"
+    fn aspect_label(&self) -> String {
+        if self.height == 0 { return "-".to_owned(); }
+        format!("{:.2}:1", self.width as f64 / self.height as f64)
+    }
+
+    fn new(name: String, [x, y, width, height]: [usize; 4]) -> Self {
+        Self { name, x, y, width, height, color: None, visible: true, locked: false, tags: Vec::new() }
+    }
+
+    /// Whether the filter text (already lowercased) occurs in the name or one of the tags.
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.name.to_lowercase().contains(filter) || self.tags.iter().any(|t| t.to_lowercase().contains(filter))
+    }
+
+    /// Whether the region reaches past the right or bottom edge of a card of size `[width, height]`.
+    fn out_of_bounds(&self, [w, h]: [usize; 2]) -> bool {
+        self.x + self.width > w || self.y + self.height > h
+    }
+
+    /// Clip the region to a card of size `[width, height]`. A region entirely off the card keeps
+    /// one pixel at the edge rather than vanishing.
+    fn clamp_to(&mut self, [w, h]: [usize; 2]) {
+        let clamp = |pos: &mut usize, len: &mut usize, limit: usize| {
+            let end = (*pos + *len).min(limit);
+            *pos = (*pos).min(limit.saturating_sub(1));
+            *len = end.saturating_sub(*pos).max(1);
+        };
+        clamp(&mut self.x, &mut self.width, w);
+        clamp(&mut self.y, &mut self.height, h);
+    }
+
+    /// Whether the two rectangles share any pixel (touching edges do not count).
+    fn overlaps(&self, other: &Self) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+/// Split comma-separated tags (`"text, icon,,art"`), dropping blanks and repeats.
+fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t == tag) { tags.push(tag.to_owned()); }
+    }
+    tags
+}
+
+/// Folder a region's image is exported to: a subfolder named after its first tag when grouping
+/// by tag (created as needed), else `dir` itself.
+fn region_export_dir(dir: &Path, r: &Region, group_by_tag: bool) -> std::io::Result<std::path::PathBuf> {
+    match r.tags.first().filter(|_| group_by_tag) {
+        Some(tag) => {
+            let sub = dir.join(sanitize_file_name(tag));
+            std::fs::create_dir_all(&sub)?;
+            Ok(sub)
+        }
+        None => Ok(dir.to_path_buf()),
+    }
+}
+
+/// Split one CSV line into fields. Fields may be quoted (`"a, b"`, with `""` for a quote).
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("fields is never empty");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Parse `name,x,y,width,height` rows. A header row and blank lines are skipped; malformed rows
+/// are reported (with their line number) without stopping the rest of the import. Returns the
+/// regions along with those reports, or an error listing them when not a single row could be read.
+fn regions_from_csv(s: &str) -> Result<(Vec<Region>, Vec<String>), String> {
+    let mut regions = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        if line.trim().is_empty() { continue; }
+        let fields = csv_fields(line);
+        let [name, x, y, w, h] = fields.as_slice() else {
+            errors.push(format!("line {}: expected 5 fields, found {}", i + 1, fields.len()));
+            continue;
+        };
+        let nums: Result<Vec<usize>, _> = [x, y, w, h].iter().map(|v| v.trim().parse::<usize>()).collect();
+        match nums.as_deref() {
+            Ok(&[x, y, width, height]) => regions.push(Region::new(name.trim().to_owned(), [x, y, width, height])),
+            // a header has no numbers at all
+            _ if i == 0 && x.trim().eq_ignore_ascii_case("x") => {}
+            _ => errors.push(format!("line {}: x, y, width and height must be whole numbers", i + 1)),
+        }
+    }
+    if regions.is_empty() {
+        return Err(if errors.is_empty() {
+            "no region rows found".to_owned()
+        } else {
+            format!("no valid rows ({})", errors.join("; "))
+        });
+    }
+    Ok((regions, errors))
+}
+
+// The parts of a TexturePacker / Aseprite "JSON (Hash)" sprite sheet that map onto regions
+#[cfg(not(target_os = "android"))]
+#[derive(serde::Deserialize)]
+struct TexturePackerSheet {
+    frames: std::collections::BTreeMap<String, TexturePackerFrame>,
+}
+
+#[cfg(not(target_os = "android"))]
+#[derive(serde::Deserialize)]
+struct TexturePackerFrame {
+    frame: TexturePackerRect,
+    #[serde(default)]
+    rotated: bool, // stored turned by 90°, so w/h are swapped in the atlas
+}
+
+#[cfg(not(target_os = "android"))]
+#[derive(serde::Deserialize)]
+struct TexturePackerRect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+
+/// Whether `len` pixels from `start` lie within `cell_len` pixels from `cell`. Ends past the range
+/// of `usize` (from untrusted files) count as outside.
+fn span_inside(start: usize, len: usize, cell: usize, cell_len: usize) -> bool {
+    start >= cell
+        && start.checked_add(len).zip(cell.checked_add(cell_len)).is_some_and(|(end, cell_end)| end <= cell_end)
+}
+
+/// Turn the frames of a `TexturePacker` "JSON (Hash)" file into regions relative to the card cell
+/// `[x, y, width, height]` (atlas pixels). Returns the regions and how many frames were skipped
+/// for not lying entirely inside the cell.
+#[cfg(not(target_os = "android"))]
+fn regions_from_texturepacker(json: &str, [cx, cy, cw, ch]: [usize; 4]) -> Result<(Vec<Region>, usize), String> {
+    let sheet: TexturePackerSheet = serde_json::from_str(json).map_err(|e| format!("Not a TexturePacker JSON (Hash) file: {e}"))?;
+    let mut regions = Vec::new();
+    let mut skipped = 0;
+    for (name, f) in sheet.frames {
+        let TexturePackerRect { x, y, w, h } = f.frame;
+        let (w, h) = if f.rotated { (h, w) } else { (w, h) };
+        if w == 0 || h == 0 || !span_inside(x, w, cx, cw) || !span_inside(y, h, cy, ch) {
+            skipped += 1;
+            continue;
+        }
+        // "hero.png" -> "hero"; the frame keys are usually the source file names
+        let name = std::path::Path::new(&name).file_stem().map_or(name.clone(), |s| s.to_string_lossy().into_owned());
+        regions.push(Region::new(name, [x - cx, y - cy, w, h]));
+    }
+    Ok((regions, skipped))
+}
+
+/// Keep the regions (in atlas pixels) that lie entirely inside the card cell `[x, y, width, height]`,
+/// made relative to it. Also returns how many were dropped.
+fn regions_to_card_space(regions: Vec<Region>, [cx, cy, cw, ch]: [usize; 4]) -> (Vec<Region>, usize) {
+    let total = regions.len();
+    let inside: Vec<Region> = regions
+        .into_iter()
+        .filter(|r| span_inside(r.x, r.width, cx, cw) && span_inside(r.y, r.height, cy, ch))
+        .map(|r| Region { x: r.x - cx, y: r.y - cy, ..r })
+        .collect();
+    let skipped = total - inside.len();
+    (inside, skipped)
+}
+
+// Version written into regions files; bump it (and branch on it in `apply_regions_file`) when the
+// format changes. Files from before the field read as version 0.
+const REGIONS_FILE_VERSION: u32 = 1;
+
+/// A saved regions file: the regions plus the card size they were drawn for.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RegionsFile {
+    #[serde(default)]
+    version: u32,
+    image_size: [usize; 2],
+    #[serde(default)]
+    coordinate_space: CoordinateSpace,
+    regions: Vec<Region>,
+}
+
+/// Human-readable name of a regions file format: the old bare list, or the object with the card
+/// size in either coordinate space.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+fn regions_file_format_name(legacy: bool, space: CoordinateSpace) -> String {
+    match (legacy, space) {
+        (true, _) => "old-style region list".to_owned(),
+        (false, CoordinateSpace::Card) => "regions file (card pixels)".to_owned(),
+        (false, CoordinateSpace::Atlas) => "regions file (atlas pixels)".to_owned(),
+    }
+}
+
+/// Which regions file format `s` is in, or None if it is not a regions file.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+fn regions_file_format(s: &str) -> Option<String> {
+    #[derive(serde::Deserialize)]
+    struct Header {
+        #[expect(dead_code, reason = "only checked to be present")]
+        image_size: [usize; 2],
+        #[serde(default)]
+        coordinate_space: CoordinateSpace,
+    }
+    if let Ok(h) = serde_json::from_str::<Header>(s) {
+        return Some(regions_file_format_name(false, h.coordinate_space));
+    }
+    serde_json::from_str::<Vec<Region>>(s).ok().map(|_| regions_file_format_name(true, CoordinateSpace::Card))
+}
+
+/// Whether an atlas "path" is an http(s) URL (desktop builds download those).
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Decode an atlas held in memory and read its card size hint.
+fn decode_atlas_bytes(bytes: &[u8]) -> Result<(image::RgbaImage, Option<[usize; 2]>), String> {
+    let img = decode_atlas(|| Ok(std::io::Cursor::new(bytes)))?;
+    let size = [img.width() as usize, img.height() as usize];
+    Ok((img, crate::png_meta::card_size_hint(bytes, size)))
+}
+
+// Quiet time after the last change to a watched atlas before it is reloaded; one save often
+// arrives as several events (truncate, write, rename)
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+
+// How long the web build waits for the card layout to stop changing before it counts the empty
+// cards, which reads the whole atlas on the UI thread
+#[cfg(target_arch = "wasm32")]
+const EMPTY_CARDS_SETTLE: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Watch on the current atlas file. The folder is watched rather than the file, because many
+/// editors save by replacing the file, which ends a watch on the file itself.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+struct AtlasWatch {
+    path: String,
+    _watcher: notify::RecommendedWatcher,
+    changes: std::sync::mpsc::Receiver<()>,
+    changed_at: Option<std::time::Instant>, // last change not reloaded yet
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+impl AtlasWatch {
+    fn new(path: &str, ctx: &egui::Context) -> notify::Result<Self> {
+        use notify::Watcher as _;
+        let file = Path::new(path).to_path_buf();
+        let dir = file.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new(".")).to_path_buf();
+        let (tx, changes) = std::sync::mpsc::channel();
+        let ctx = ctx.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else { return };
+            let ours = event.paths.iter().any(|p| p.file_name().is_some_and(|n| Some(n) == file.file_name()));
+            if ours && (event.kind.is_modify() || event.kind.is_create()) && tx.send(()).is_ok() {
+                ctx.request_repaint();
+            }
+        })?;
+        watcher.watch(&dir, notify::RecursiveMode::NonRecursive)?;
+        Ok(Self { path: path.to_owned(), _watcher: watcher, changes, changed_at: None })
+    }
+}
+
+/// Download and decode an atlas (blocking; run off the UI thread). HTTP errors and responses
+/// that are not images are reported as such rather than as decode failures.
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+fn fetch_atlas(url: &str) -> Result<(image::RgbaImage, Option<[usize; 2]>), String> {
+    use std::io::Read as _;
+    let resp = ureq::get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, resp) => format!("the server answered HTTP {code} {}", resp.status_text()),
+        ureq::Error::Transport(t) => format!("request failed: {t}"),
+    })?;
+    let content_type = resp.content_type().to_owned();
+    // octet-stream is what many file servers send for any download
+    if !content_type.starts_with("image/") && content_type != "application/octet-stream" {
+        return Err(format!("the server sent '{content_type}', not an image"));
+    }
+    let too_large = || format!("the atlas is larger than {} MiB", MAX_DOWNLOAD_BYTES / (1024 * 1024));
+    if resp.header("Content-Length").and_then(|len| len.trim().parse::<u64>().ok()).is_some_and(|len| len > MAX_DOWNLOAD_BYTES) {
+        return Err(too_large());
+    }
+    // One byte past the limit tells a download that was cut off from one that just fits
+    let mut bytes = Vec::new();
+    resp.into_reader()
+        .take(MAX_DOWNLOAD_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("download failed: {e}"))?;
+    if bytes.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(too_large());
+    }
+    decode_atlas_bytes(&bytes)
+}
+
+/// Atlas file extensions that can be opened (lowercase, without the dot). Keep in sync with the
+/// `image` crate features in Cargo.toml.
+pub(crate) fn supported_extensions() -> &'static [&'static str] {
+    &["png", "jpg", "jpeg", "webp", "bmp"]
+}
+
+/// Decode an atlas image from a reader produced by `open`, sniffing the format from the content.
+/// On failure the error names the detected format and, when the header is readable, the
+/// dimensions, which tells a corrupt file apart from an unsupported one.
+pub(crate) fn decode_atlas<R: std::io::BufRead + std::io::Seek>(
+    open: impl Fn() -> std::io::Result<R>,
+) -> Result<image::RgbaImage, String> {
+    let sniff = || image::io::Reader::new(open()?).with_guessed_format();
+    let reader = sniff().map_err(|e| e.to_string())?;
+    let format = reader.format();
+    match reader.decode() {
+        Ok(img) => Ok(img.to_rgba8()),
+        Err(e) => {
+            let format = format.map_or_else(|| "unrecognized".to_owned(), |f| format!("{f:?}"));
+            let size = sniff()
+                .ok()
+                .and_then(|r| r.into_dimensions().ok())
+                .map_or_else(|| "unknown".to_owned(), |(w, h)| format!("{w}x{h}"));
+            Err(format!("{e} (detected format: {format}, dimensions: {size})"))
+        }
+    }
+}
+
+/// Card index for a jump entry: a flat index, or `row,col` counted from 0. Out-of-range values are
+/// clamped to the grid; text that is neither form is an error.
+fn parse_card_ref(input: &str, cols: usize, rows: usize) -> Result<usize, String> {
+    let max = (cols * rows).saturating_sub(1);
+    let input = input.trim();
+    if let Some((row, col)) = input.split_once(',') {
+        let parse = |v: &str| v.trim().parse::<usize>().map_err(|e| format!("'{input}' is not row,col: {e}"));
+        let (row, col) = (parse(row)?, parse(col)?);
+        Ok(row.min(rows.saturating_sub(1)) * cols + col.min(cols.saturating_sub(1)))
+    } else {
+        let index = input.parse::<usize>().map_err(|e| format!("'{input}' is neither an index nor row,col: {e}"))?;
+        Ok(index.min(max))
+    }
+}
+
+// Display order of the regions list (the stored order, used for indices and files, is unchanged)
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+enum RegionSort {
+    #[default]
+    Created,
+    Name,
+    Area,
+}
+
+// How the card preview is sized before the user's zoom: fitted to the viewport along one or both
+// axes, or one card pixel per screen point. Except for Fit, the card may overflow and the wheel scrolls.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+enum ViewMode {
+    #[default]
+    Fit,
+    FitWidth,
+    FitHeight,
+    ActualSize,
+}
+
+impl ViewMode {
+    const ALL: [Self; 4] = [Self::Fit, Self::FitWidth, Self::FitHeight, Self::ActualSize];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Fit => "Fit",
+            Self::FitWidth => "Fit width",
+            Self::FitHeight => "Fit height",
+            Self::ActualSize => "100%",
+        }
+    }
+
+    /// Base preview scale for a card of `card` points in a viewport of `view` points.
+    fn scale(self, card: egui::Vec2, view: egui::Vec2) -> f32 {
+        let (scale_x, scale_y) = (view.x / card.x, view.y / card.y);
+        match self {
+            Self::Fit => scale_x.min(scale_y).clamp(0.1, 4.0),
+            Self::FitWidth => scale_x.clamp(0.01, 4.0),
+            Self::FitHeight => scale_y.clamp(0.01, 4.0),
+            Self::ActualSize => 1.0,
+        }
+    }
+}
+
+// What is painted behind the card preview, so transparent card areas can be told apart
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+enum PreviewBackground {
+    #[default]
+    None, // the panel background shows through
+    Checkerboard,
+    White,
+    Black,
+    Custom,
+}
+
+impl PreviewBackground {
+    const ALL: [Self; 5] = [Self::None, Self::Checkerboard, Self::White, Self::Black, Self::Custom];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Checkerboard => "Checkerboard",
+            Self::White => "White",
+            Self::Black => "Black",
+            Self::Custom => "Custom",
+        }
+    }
+}
+
+// Side of a checkerboard square behind the preview, in points
+const CHECKER_SIZE: f32 = 8.0;
+
+// How the card preview is sampled when scaled: sharp pixels for inspection or smooth for artwork
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+enum TextureFilter {
+    #[default]
+    Nearest,
+    Linear,
+}
+
+impl TextureFilter {
+    fn options(self) -> TextureOptions {
+        match self {
+            Self::Nearest => TextureOptions::NEAREST,
+            Self::Linear => TextureOptions::LINEAR,
+        }
+    }
+}
+
+// What the cached region crop was built from: card rect in the atlas and region rect (both x,y,w,h).
+// Any change to these means the crop texture is stale.
+type RegionPreviewKey = ([usize; 4], [usize; 4]);
+
+// What region coordinates in a saved regions file are relative to: the card (default) or the
+// whole atlas, for the card that was current when saving
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum CoordinateSpace {
+    #[default]
+    Card,
+    Atlas,
+}
+
+#[cfg(not(target_os = "android"))]
+// One file describing the whole atlas for a data-driven loader: the card grid and each card's
+// regions, either repeated per card or as one shared list (`regions`) that every card uses
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+struct AtlasManifest {
+    atlas: String,
+    card_width: usize,
+    card_height: usize,
+    cols: usize,
+    rows: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    regions: Option<Vec<Region>>,
+    cards: Vec<ManifestCard>,
+}
+
+#[cfg(not(target_os = "android"))]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+struct ManifestCard {
+    index: usize,
+    // top-left corner in the atlas, so loaders need not know about margins and spacing
+    x: usize,
+    y: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    regions: Option<Vec<Region>>,
+}
+
+// A named group of cards within the atlas (e.g. player cards, then fortress cards from some index)
+// with its own card size and regions
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+struct CardSet {
+    name: String,
+    start_index: usize,
+    card_width: usize,
+    card_height: usize,
+    regions: Vec<Region>,
+}
+
+// An "Export all..." run in progress; a few cards are written per frame so the UI keeps updating
+struct BatchExport {
+    dir: std::path::PathBuf,
+    next: usize,
+    written: usize,
+    // Export these regions (with their file names) of every card instead of the whole cards.
+    // Snapshot taken when the run starts, so editing regions meanwhile does not affect it.
+    regions: Option<Vec<(Region, String)>>,
+    group_by_tag: bool,
+    skip_empty: bool,
+}
+
+// Outcome of decoding an atlas off the UI thread: the image and its card size hint
+type AtlasLoadResult = Result<(image::RgbaImage, Option<[usize; 2]>), String>;
+
+// Largest atlas download accepted from a URL
+#[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+const MAX_DOWNLOAD_BYTES: u64 = 512 * 1024 * 1024;
+
+// How many cards a batch export writes per frame
+const EXPORT_CARDS_PER_FRAME: usize = 4;
+
+// Longer side of the preview's mini-map, in points
+const MINIMAP_SIDE: f32 = 140.0;
+
+// Loupe: side of the floating box in points, and how many points one card pixel covers in it
+const LOUPE_SIDE: f32 = 128.0;
+const LOUPE_ZOOM: f32 = 8.0;
+
+// Thickness of the preview's scrollbars (scrolling view modes only)
+const SCROLLBAR_WIDTH: f32 = 8.0;
+
+// Smallest drag threshold used for touch input, in points; a fingertip wobbles more than a mouse
+const TOUCH_DRAG_THRESHOLD: f32 = 12.0;
+
+// Pixels of the neighbouring cards shown around the preview with "Bleed" on
+const BLEED_PX: usize = 4;
+
+// Filmstrip: cards shown on each side of the current one, thumbnail width in pixels, and how many
+// missing thumbnails are generated per frame
+const FILMSTRIP_RADIUS: usize = 12;
+const THUMBNAIL_WIDTH: u32 = 80;
+const THUMBNAILS_PER_FRAME: usize = 4;
+
+// Grips drawn around the selected region; dragging one resizes the region from that side/corner
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RegionGrip {
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+}
+
+impl RegionGrip {
+    const ALL: [Self; 8] = [
+        Self::TopLeft,
+        Self::Top,
+        Self::TopRight,
+        Self::Right,
+        Self::BottomRight,
+        Self::Bottom,
+        Self::BottomLeft,
+        Self::Left,
+    ];
+
+    // Which edges of the region follow the pointer: (left, top, right, bottom)
+    fn edges(self) -> (bool, bool, bool, bool) {
+        match self {
+            Self::TopLeft => (true, true, false, false),
+            Self::Top => (false, true, false, false),
+            Self::TopRight => (false, true, true, false),
+            Self::Right => (false, false, true, false),
+            Self::BottomRight => (false, false, true, true),
+            Self::Bottom => (false, false, false, true),
+            Self::BottomLeft => (true, false, false, true),
+            Self::Left => (true, false, false, false),
+        }
+    }
+
+    /// Position of the grip on a region's screen rectangle
+    fn anchor(self, rect: egui::Rect) -> egui::Pos2 {
+        let (l, t, r, b) = self.edges();
+        let x = if l { rect.left() } else if r { rect.right() } else { rect.center().x };
+        let y = if t { rect.top() } else if b { rect.bottom() } else { rect.center().y };
+        egui::pos2(x, y)
+    }
+
+    /// Grip under `pos`, if any (corners win over edges where they overlap)
+    fn hit(rect: egui::Rect, pos: egui::Pos2) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|g| egui::Rect::from_center_size(g.anchor(rect), egui::Vec2::splat(GRIP_SIZE)).contains(pos))
+    }
+
+    /// Move the grip's edges of `orig` (x,y,w,h) to the card pixel `(px, py)`, keeping at least 1×1.
+    fn resize(self, orig: [usize; 4], px: usize, py: usize) -> [usize; 4] {
+        let [x, y, w, h] = orig;
+        let (mut left, mut top, mut right, mut bottom) = (x, y, x + w, y + h);
+        let (l, t, r, b) = self.edges();
+        if l { left = px.min(right.saturating_sub(1)); }
+        if r { right = px.max(left + 1); }
+        if t { top = py.min(bottom.saturating_sub(1)); }
+        if b { bottom = py.max(top + 1); }
+        [left, top, right - left, bottom - top]
+    }
+}
+
+// What a drag on the selected region does
+#[derive(Clone, Copy)]
+enum RegionEditKind {
+    Resize(RegionGrip),
+    Move,
+}
+
+// A resize/move drag in progress on the selected region
+struct RegionEdit {
+    index: usize,
+    kind: RegionEditKind,
+    orig: [usize; 4],        // region rect (x,y,w,h) when the drag started
+    press: (usize, usize),   // card pixel where the drag started
+    press_pos: egui::Pos2,   // screen position of the press
+    active: bool,            // false until a move passes the drag threshold; until then it may be a click
+    snapshot: Vec<Region>,   // regions before the drag, pushed to undo if anything changed
+}
+
+impl RegionEdit {
+    /// Region rect for the pointer at card pixel `(px, py)`. Moves keep the region inside the card.
+    /// With a `snap` step, resized edges and the moved region's top-left corner land on the snap grid.
+    fn apply(&self, px: usize, py: usize, card: [usize; 2], snap: usize) -> [usize; 4] {
+        match self.kind {
+            RegionEditKind::Resize(grip) => grip.resize(self.orig, snap_to(px, snap).min(card[0]), snap_to(py, snap).min(card[1])),
+            RegionEditKind::Move => {
+                let [x, y, w, h] = self.orig;
+                let nx = snap_to((x + px).saturating_sub(self.press.0), snap).min(card[0].saturating_sub(w));
+                let ny = snap_to((y + py).saturating_sub(self.press.1), snap).min(card[1].saturating_sub(h));
+                [nx, ny, w, h]
+            }
+        }
+    }
+}
+
+// Side of the square grips in screen points
+const GRIP_SIZE: f32 = 8.0;
+
+// Snap step used when snapping is switched on; snap gridlines closer than this many points are not drawn
+const DEFAULT_SNAP_STEP: usize = 8;
+const MIN_SNAP_LINE_GAP: f32 = 4.0;
+
+// From this many screen points per card pixel on, the preview outlines every pixel
+const PIXEL_GRID_MIN_SCALE: f32 = 8.0;
+
+/// Nearest multiple of `step` (`step` 0 leaves the value as is).
+fn snap_to(v: usize, step: usize) -> usize {
+    (v + step / 2).checked_div(step).map_or(v, |n| n * step)
+}
+
+/// Card-pixel rectangle `[x, y, w, h]` spanned by a drag from `start` to `end` over the card shown
+/// in `img_rect` at `scale` points per card pixel. Everything here is in egui points (pointer
+/// positions, the image rect and `scale` alike), so the display's `pixels_per_point` cancels out
+/// and never needs to enter the math. Both edges are rounded to the nearest pixel boundary (rather
+/// than the corner and the size separately) so the rectangle does not drift by a pixel at
+/// fractional scales; it stays on the card and is at least one pixel in size.
+fn drag_to_card_rect(img_rect: egui::Rect, scale: f32, start: egui::Pos2, end: egui::Pos2, [card_w, card_h]: [usize; 2]) -> [usize; 4] {
+    let to_px = |p: f32, len: usize| ((p / scale).round().max(0.0) as usize).min(len);
+    let (a, b) = (start - img_rect.min, end - img_rect.min);
+    let (x0, x1) = (to_px(a.x.min(b.x), card_w), to_px(a.x.max(b.x), card_w));
+    let (y0, y1) = (to_px(a.y.min(b.y), card_h), to_px(a.y.max(b.y), card_h));
+    let (w, h) = ((x1 - x0).max(1), (y1 - y0).max(1));
+    [x0.min(card_w.saturating_sub(w)), y0.min(card_h.saturating_sub(h)), w, h]
+}
+
+/// Part of the card shown in the loupe around card position `center` (in card pixels), clipped to
+/// a card of `card_size`, and where that part lands in a `LOUPE_SIDE` box at `LOUPE_ZOOM`, relative
+/// to the box's top-left corner. Near the card edges the rest of the box stays empty.
+fn loupe_source(center: egui::Pos2, card_size: egui::Vec2) -> (egui::Rect, egui::Rect) {
+    let full = egui::Rect::from_center_size(center, egui::Vec2::splat(LOUPE_SIDE / LOUPE_ZOOM));
+    let src = full.intersect(egui::Rect::from_min_size(egui::Pos2::ZERO, card_size));
+    let dest = egui::Rect::from_min_size(egui::Pos2::ZERO + (src.min - full.min) * LOUPE_ZOOM, src.size() * LOUPE_ZOOM);
+    (src, dest)
+}
+
+/// Snap the edges of a region rect (x,y,w,h) to multiples of `step`, keeping it on the card and at
+/// least one step (or pixel, at the card edge) in size.
+fn snap_rect([x, y, w, h]: [usize; 4], step: usize, card: [usize; 2]) -> [usize; 4] {
+    if step == 0 { return [x, y, w, h]; }
+    let snap_span = |start: usize, len: usize, max: usize| {
+        let a = snap_to(start, step).min(max.saturating_sub(1));
+        let mut b = snap_to(start + len, step).min(max);
+        if b <= a { b = (a + step).min(max).max(a + 1); }
+        (a, b - a)
+    };
+    let (x, w) = snap_span(x, w, card[0]);
+    let (y, h) = snap_span(y, h, card[1]);
+    [x, y, w, h]
+}
+
+// Actions reachable from the command palette
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PaletteAction {
+    OpenAtlas,
+    ReloadAtlas,
+    SaveRegions,
+    LoadRegions,
+    ExportCard,
+    ExportAllCards,
+    ExportRegions,
+    ToggleRegionsPanel,
+    CenterOnRegion,
+    PrevCard,
+    NextCard,
+    FirstCard,
+    LastCard,
+    JumpTo(usize),
+}
+
+impl PaletteAction {
+    const ALL: &[Self] = &[
+        Self::OpenAtlas,
+        Self::ReloadAtlas,
+        Self::SaveRegions,
+        Self::LoadRegions,
+        Self::ExportCard,
+        Self::ExportAllCards,
+        Self::ExportRegions,
+        Self::ToggleRegionsPanel,
+        Self::CenterOnRegion,
+        Self::PrevCard,
+        Self::NextCard,
+        Self::FirstCard,
+        Self::LastCard,
+    ];
+
+    fn label(self) -> String {
+        match self {
+            Self::OpenAtlas => "Open atlas...".to_owned(),
+            Self::ReloadAtlas => "Reload atlas".to_owned(),
+            Self::SaveRegions => "Save regions...".to_owned(),
+            Self::LoadRegions => "Load regions...".to_owned(),
+            Self::ExportCard => "Export card as PNG...".to_owned(),
+            Self::ExportAllCards => "Export all cards...".to_owned(),
+            Self::ExportRegions => "Export region crops...".to_owned(),
+            Self::ToggleRegionsPanel => "Toggle regions panel".to_owned(),
+            Self::CenterOnRegion => "Center on selected region".to_owned(),
+            Self::PrevCard => "Previous card".to_owned(),
+            Self::NextCard => "Next card".to_owned(),
+            Self::FirstCard => "First card".to_owned(),
+            Self::LastCard => "Last card".to_owned(),
+            Self::JumpTo(i) => format!("Jump to card {i}"),
+        }
+    }
+
+    /// Actions matching `query`: every whitespace-separated word must appear in the label
+    /// (case-insensitive). A bare number offers "Jump to card N" first.
+    fn matching(query: &str) -> Vec<Self> {
+        let query = query.trim().to_lowercase();
+        let mut out: Vec<Self> = query.parse().map(Self::JumpTo).into_iter().collect();
+        out.extend(Self::ALL.iter().copied().filter(|a| {
+            let label = a.label().to_lowercase();
+            query.split_whitespace().all(|w| label.contains(w))
+        }));
+        out
+    }
+}
+
+// Atlas work that outlives a frame: decoding in the background, watching the file, and what a
+// load is still waiting for
+#[derive(Default)]
+struct AtlasJobs {
+    #[cfg(not(target_arch = "wasm32"))]
+    atlas_loading: Option<(String, std::sync::mpsc::Receiver<(AtlasLoadResult, std::time::Duration)>)>, // path being decoded in the background, and how long that took
+    atlas_load_time: Option<std::time::Duration>, // how long the current atlas took to load (native only)
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    atlas_watch: Option<AtlasWatch>,
+    missing_atlas: Option<String>, // last session's atlas path that no longer exists
+    pending_preset_size: Option<(String, [usize; 2])>, // card size of the sample atlas being loaded, applied once it is installed
+    #[cfg(not(target_arch = "wasm32"))]
+    empty_cards_count: Option<(GridLayout, std::sync::mpsc::Receiver<usize>)>, // layout whose empty cards are being counted in the background
+    #[cfg(target_arch = "wasm32")]
+    empty_cards_since: Option<(GridLayout, f64)>, // layout waiting to settle before its empty cards are counted, and since when
+}
+
+// Textures and counts derived from the atlas; rebuilt when the atlas, card or layout changes
+#[derive(Default)]
+struct PreviewCache {
+    atlas_texture: Option<egui::TextureHandle>, // full atlas for the grid overview
+    bleed_texture: Option<egui::TextureHandle>, // current card plus `BLEED_PX` on each side
+    thumbnails: std::collections::HashMap<usize, egui::TextureHandle>, // only cards near `index` are kept
+    thumbnails_layout: [usize; 6], // card size, margins and spacing the cached thumbnails were cut with
+    region_preview: Option<(RegionPreviewKey, egui::TextureHandle)>, // crop of the selected region
+    empty_cards: Option<(GridLayout, usize)>, // number of empty cards in the current atlas with that layout
+}
+
+// Region undo/redo, and the regions before each kind of edit that is still in progress
+#[derive(Default)]
+struct EditHistory {
+    undo_stack: Vec<Vec<Region>>, // snapshots of `regions` before each edit, newest last
+    redo_stack: Vec<Vec<Region>>,
+    region_fields_snapshot: Option<Vec<Region>>, // regions before the numeric edit in progress
+    nudge_snapshot: Option<Vec<Region>>, // regions before the arrow-key nudging in progress
+    card_resize_base: Option<((usize, usize), Vec<Region>)>, // card size and regions before a size edit in progress
+}
+
+// Text being typed into fields whose value is only applied on confirm or while focused
+#[derive(Default)]
+struct TextInputs {
+    tags_input: Option<(usize, String)>, // tags text of the region being edited, while the field has focus
+    jump_input: String, // "index" or "row,col" typed next to the index field
+    context_rename: String, // name being typed in the preview's right-click menu
+    region_filter: String, // case-insensitive name substring the regions list is narrowed to
+}
+
+// What a text file picked in the browser is imported as; the file arrives in a later frame
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy)]
+enum WebImport {
+    Regions,
+    RegionsCsv,
+    TexturePacker,
+    Settings,
+}
+
+// Dialogs and popups waiting for the user
+#[derive(Default)]
+struct Prompts {
+    new_preset_name: Option<String>, // name being typed for "Save as preset..."
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    url_input: Option<String>, // URL being typed for "Open URL..."
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    pending_regions_overwrite: Option<(std::path::PathBuf, String)>, // save target in another format, and that format
+    #[cfg(target_arch = "wasm32")]
+    web_import: Option<WebImport>, // what the text file being picked in the browser is for
+    card_size_hint: Option<[usize; 2]>, // card size read from the atlas PNG metadata, awaiting confirmation
+    // Command palette (Ctrl+P) state
+    palette_open: bool,
+    palette_query: String,
+    palette_selected: usize, // highlighted entry of the filtered command list
+    confirm_reset: bool, // "Reset app" was picked, waiting for the user to confirm
+    confirm_clear: bool, // "Clear All" regions was clicked, waiting for the user to confirm
+}
+
+// Card steps taken by themselves: repeat of a held arrow key and the slideshow
+#[derive(Default)]
+struct AutoAdvance {
+    nav_repeat: Option<(i64, f64)>, // held direction and time of the next repeat step
+    slideshow_next: Option<f64>, // time of the next advance while playing
+}
+
+// Measure tool: while on, dragging on the preview draws a ruler instead of a region
+#[derive(Default)]
+struct MeasureTool {
+    active: bool,
+    ends: Option<(egui::Pos2, egui::Pos2)>, // ruler ends in (view-oriented) card pixels
 }
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -30,8 +901,30 @@ pub struct TemplateApp {
     // Persist the last opened atlas path (optional)
     atlas_path: Option<String>,
 
+    // Atlas files loaded successfully, most recent first (at most MAX_RECENT_ATLASES)
+    recent_atlases: std::collections::VecDeque<String>,
+
+    // Atlas manifest lists the regions once instead of per card
+    manifest_shared_regions: bool,
+
+    // SVG export carries the card image as its background
+    svg_embed_card: bool,
+
+    // Region image exports go into one subfolder per (first) tag
+    export_group_by_tag: bool,
+
+    // Whether "Save..." writes regions relative to the card or in atlas pixels of the current card
+    save_coordinate_space: CoordinateSpace,
+
+    // Folder the last file dialog picked from or saved to; the next dialog opens there
+    last_dir: Option<String>,
+
+    // Reload the atlas by itself when its file changes on disk
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    watch_atlas: bool,
+
     #[serde(skip)]
-    atlas: Option<image::RgbaImage>,
+    atlas: Option<std::sync::Arc<image::RgbaImage>>, // shared with the thread counting empty cards
 
     #[serde(skip)]
     atlas_size: [usize; 2],
@@ -40,9 +933,34 @@ pub struct TemplateApp {
     card_width: usize,
     card_height: usize,
 
-    // Selected preset index into CARD_FORMATS or None for custom
+    // Exact card size for atlases scaled to fractional cells (e.g. 534.7 px); the width / height
+    // above are this rounded, and cards are placed at rounded multiples of it so the error does not
+    // add up across columns
+    card_pitch: Option<[f32; 2]>,
+
+    // Atlas layout: empty border before the first card and gap between neighbouring cards, in pixels
+    margin_x: usize,
+    margin_y: usize,
+    spacing_x: usize,
+    spacing_y: usize,
+
+    // Shift of the whole grid in pixels on top of the margin, for sheets that are slightly off
+    offset_x: i32,
+    offset_y: i32,
+
+    // Selected preset index into `card_formats()` or None for custom
     selected_preset: Option<usize>,
 
+    // Card formats saved by the user: (label, width, height)
+    user_presets: Vec<(String, usize, usize)>,
+
+    // Card sets and the active one; its card size and regions live in the fields above while active
+    card_sets: Vec<CardSet>,
+    current_set: Option<usize>,
+
+    // Names of pinned card format presets, in pin order; shown at the top of the format combo
+    pinned_presets: Vec<String>,
+
     // Selected atlas preset index (into ATLAS_PRESETS) or None
     selected_atlas: Option<usize>,
 
@@ -56,14 +974,60 @@ pub struct TemplateApp {
     #[serde(skip)]
     texture: Option<egui::TextureHandle>,
 
+    // Show the whole atlas with the card grid drawn over it instead of a single card
+    show_grid: bool,
+
+    // Strip of card thumbnails around the current index along the bottom of the window
+    show_filmstrip: bool,
+
+    // Review notes per card index, edited in the notes panel; cards without a note have no entry
+    card_notes: std::collections::HashMap<usize, String>,
+    show_notes: bool,
+
+    #[serde(skip)]
+    filmstrip_focus: Option<usize>, // index the strip last scrolled to
+
     #[serde(skip)]
     last_index: Option<usize>,
 
     #[serde(skip)]
     error: Option<String>,
 
+    #[serde(skip)]
+    warning: Option<String>, // non-fatal notice (e.g. values adjusted on load); the preview stays usable
+
+    #[serde(skip)]
+    status: Option<String>, // informational message (progress, last completed action)
+
+    #[serde(skip)]
+    batch_export: Option<BatchExport>, // "Export all..." run in progress
+
     // Regions editor state:
-    regions: Vec<Region>, // saved regions (coordinates in card pixels)
+    // Regions of the current atlas (coordinates in card pixels). Only read from storage for
+    // migration: older versions kept one flat list for every atlas. Saved via `regions_by_atlas`.
+    #[serde(skip_serializing)]
+    regions: Vec<Region>,
+
+    // Region sets of all atlases, keyed by `atlas_path`
+    regions_by_atlas: std::collections::HashMap<String, Vec<Region>>,
+
+    // Card size last used with each atlas, keyed by `atlas_path`
+    card_size_by_atlas: std::collections::HashMap<String, [usize; 2]>,
+
+    // Region selection (primary, all) last used with each atlas, keyed by `atlas_path`
+    selection_by_atlas: std::collections::HashMap<String, (Option<usize>, std::collections::BTreeSet<usize>)>,
+
+    #[serde(skip)]
+    region_edit: Option<RegionEdit>,
+
+    #[serde(skip)]
+    highlighted_overlap: Option<(usize, usize)>, // overlapping pair picked from the panel warning
+
+    // Scale regions along with the card size when it is changed by hand or from a format preset
+    rescale_regions_with_card: bool,
+
+    #[serde(skip)]
+    paste_region_requested: bool, // "Paste region" is waiting for the clipboard text
 
     #[serde(skip)]
     drag_start: Option<egui::Pos2>,
@@ -77,43 +1041,222 @@ pub struct TemplateApp {
     #[serde(skip)]
     new_region_name: String,
 
-    #[serde(skip)]
-    selected_region: Option<usize>,
+    // Selection of the current atlas's regions. Like `regions`, only read from storage for
+    // migration; saved via `selection_by_atlas` so a restart keeps the working context.
+    #[serde(skip_serializing)]
+    selected_region: Option<usize>, // primary selection: the region with grips and the detail editor
 
-    #[serde(skip)]
-    dragging: bool,
+    #[serde(skip_serializing)]
+    selected_regions: std::collections::BTreeSet<usize>, // every selected region, including the primary one
 
     #[serde(skip)]
-    last_pointer_down: bool,
+    context_region: Option<usize>, // region the preview's right-click menu acts on
 
     #[serde(skip)]
-    recent_events: std::collections::VecDeque<String>,
+    dragging: bool,
 
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
     #[serde(skip)]
-    recent_events_paused: bool,
+    clipboard: Option<arboard::Clipboard>, // created on first "Copy card"
 
     #[serde(skip)]
-    event_dump: Option<String>,
+    debug_readout: Vec<String>, // pointer state of the preview, shown in the status bar
 
     #[serde(skip)]
     pointer_down_on_image: bool,
 
     /// Runtime toggle to show/hide the regions SidePanel on native builds
     show_regions_panel: bool,
-}
 
-const ATLAS_PATH: &str = "assets/light_cards.png"; // Default atlas path; use Open... to pick a different file
+    // Width of the regions panel as the user last resized it
+    regions_panel_width: f32,
 
-// Hardcoded card format presets: (label, width, height)
-const CARD_FORMATS: &[(&str, usize, usize)] = &[
-    ("Player cards (535×752)", 535, 752),
-    ("Fortress (1380x912)", 1380, 912),
-    ("Path (1380x912)", 1380, 912),
-];
+    // Order of the regions list in the panel
+    region_sort: RegionSort,
 
-// Bundled atlas presets for quick selection (label, asset path, card width, card height)
-const ATLAS_PRESETS: &[(&str, &str, usize, usize)] = &[
-    ("Light cards", "assets/light_cards.png", 535, 752),
+    // Sampling of the scaled card preview
+    texture_filter: TextureFilter,
+
+    // Base size of the card preview (before zoom)
+    view_mode: ViewMode,
+
+    // Show a few pixels of the neighbouring cards around the preview to check the crop
+    show_bleed: bool,
+
+    // Magnified view of the card pixels under the cursor, for placing region edges exactly
+    show_loupe: bool,
+
+    // Painted behind the card preview; `background_color` is used for `Custom`
+    preview_background: PreviewBackground,
+    background_color: [u8; 3],
+
+    // Orientation of the viewed and exported card: clockwise rotation in degrees (0/90/180/270),
+    // applied after the flips. Regions stay in atlas orientation, so editing them is disabled
+    // while any of this is set.
+    card_rotation: u16,
+    flip_h: bool,
+    flip_v: bool,
+
+    // Region edges snap to multiples of this many card pixels while drawing/resizing (0 = off)
+    snap_step: usize,
+
+    // Region overlay look: outline width in screen points and opacity of the selected/pending fill
+    overlay_stroke_width: f32,
+    overlay_fill_alpha: u8,
+
+    // Pointer travel in screen points after which a press on the card becomes a drag (drawing a
+    // region) instead of a click (selecting one)
+    drag_threshold: f32,
+    // Whether the latest pointer input came from a touch screen (raises the drag threshold)
+    #[serde(skip)]
+    touch_input: bool,
+
+    // Preview view transform: zoom is relative to the fitted size (1.0 = fit), pan is in screen points
+    zoom: f32,
+    pan_offset: egui::Vec2,
+
+    // Set by "Center on region"; the preview zooms to the selected region on its next frame
+    #[serde(skip)]
+    center_on_region: bool,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    window_title: String, // last title sent to the window
+
+    // Native window geometry from the last session, in points: inner size and outer top-left position
+    window_size: [f32; 2],
+    window_pos: Option<[f32; 2]>,
+
+    // Keyboard repeat for Left/Right card navigation (delay in seconds, rate in steps per second)
+    key_repeat_delay: f32,
+    key_repeat_rate: f32,
+
+    // Card navigation and "Export all" pass over fully transparent cards
+    skip_empty_cards: bool,
+
+    // Slideshow: seconds each card is shown while playing
+    slideshow_interval: f32,
+
+    #[serde(skip)]
+    atlas_jobs: AtlasJobs,
+
+    #[serde(skip)]
+    previews: PreviewCache,
+
+    #[serde(skip)]
+    history: EditHistory,
+
+    #[serde(skip)]
+    inputs: TextInputs,
+
+    #[serde(skip)]
+    prompts: Prompts,
+
+    #[serde(skip)]
+    measure: MeasureTool,
+
+    #[serde(skip)]
+    auto_advance: AutoAdvance,
+}
+
+/// Reorient a card for display/export: mirror it first, then rotate it clockwise by `rotation`
+/// degrees (anything but 90/180/270 leaves it unrotated).
+fn transform_card(img: image::RgbaImage, rotation: u16, flip_h: bool, flip_v: bool) -> image::RgbaImage {
+    use image::imageops;
+    let mut img = img;
+    if flip_h { imageops::flip_horizontal_in_place(&mut img); }
+    if flip_v { imageops::flip_vertical_in_place(&mut img); }
+    match rotation {
+        90 => imageops::rotate90(&img),
+        180 => imageops::rotate180(&img),
+        270 => imageops::rotate270(&img),
+        _ => img,
+    }
+}
+
+/// Turn a region name into a safe file stem: anything but ASCII letters, digits, `-` and `_`
+/// becomes `_` (this covers path separators, spaces and dots).
+fn sanitize_file_name(name: &str) -> String {
+    let cleaned: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if cleaned.is_empty() { "region".to_owned() } else { cleaned }
+}
+
+/// Encode an image as PNG bytes (for downloads where there is no file path to save to, and for
+/// embedding).
+fn encode_png(img: &image::RgbaImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Standard base64 with padding, for `data:` URLs.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk.first(), chunk.get(1), chunk.get(2)].map(|b| u32::from(b.copied().unwrap_or(0)));
+        let n = (b[0] << 16) | (b[1] << 8) | b[2];
+        for i in 0..4 {
+            // a chunk of k bytes fills k + 1 characters; the rest is padding
+            let sextet = ALPHABET.get((n >> (18 - 6 * i)) as usize & 63).filter(|_| i <= chunk.len());
+            out.push(sextet.map_or('=', |&c| char::from(c)));
+        }
+    }
+    out
+}
+
+/// Escape text for use in XML content and attribute values.
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Clamp a card size into `1..=MAX_CARD_SIDE`. The flag reports whether anything had to change.
+fn clamp_card_size(w: usize, h: usize) -> ([usize; 2], bool) {
+    let clamped = [w.clamp(1, MAX_CARD_SIDE), h.clamp(1, MAX_CARD_SIDE)];
+    (clamped, clamped != [w, h])
+}
+
+// Maximum number of region snapshots kept for undo
+const UNDO_DEPTH: usize = 50;
+
+// Length of the File > Recent list
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_RECENT_ATLASES: usize = 10;
+
+// Preview zoom limits (relative to the fitted size) and scroll-wheel sensitivity
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 32.0;
+const ZOOM_SPEED: f32 = 0.002;
+
+// Share of the viewport (along the tighter axis) that "Center on region" fills with the region
+const CENTER_REGION_FILL: f32 = 0.6;
+
+// Native window title; the loaded atlas file name is appended
+#[cfg(not(target_arch = "wasm32"))]
+pub const WINDOW_TITLE: &str = "wotr_helper";
+
+const ATLAS_PATH: &str = "assets/light_cards.png"; // Default atlas path; use Open... to pick a different file
+
+// Built-in card format presets: (label, width, height). User presets are listed after these.
+// The persisted `selected_preset` indexes this list and pins refer to the labels, so existing
+// entries keep their position and name; new ones go at the end.
+const CARD_FORMATS: &[(&str, usize, usize)] = &[
+    ("Player cards (535×752)", 535, 752),
+    ("Fortress (1380x912)", 1380, 912),
+    ("Path (1380x912)", 1380, 912),
+    ("Poker 2.5×3.5\" @ 300 dpi (750×1050)", 750, 1050),
+    ("Tarot 2.75×4.75\" @ 300 dpi (825×1425)", 825, 1425),
+    ("Mini 1.75×2.5\" @ 300 dpi (525×750)", 525, 750),
+];
+
+// Bundled atlas presets for quick selection (label, asset path, card width, card height)
+const ATLAS_PRESETS: &[(&str, &str, usize, usize)] = &[
+    ("Light cards", "assets/light_cards.png", 535, 752),
     ("Dark cards", "assets/dark_cards.png", 535, 752),
     ("Light fortresses", "assets/light_fortresses.png", 1380, 912),
     ("Dark fortresses", "assets/dark_fortresses.png", 1380, 912),
@@ -137,6 +1280,14 @@ impl Default for TemplateApp {
             // viewer defaults
             index: 0,
             atlas_path: Some(ATLAS_PATH.to_string()),
+            recent_atlases: std::collections::VecDeque::new(),
+            last_dir: None,
+            save_coordinate_space: CoordinateSpace::Card,
+            manifest_shared_regions: false,
+            svg_embed_card: true,
+            export_group_by_tag: false,
+            #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+            watch_atlas: false,
             atlas: None,
             atlas_size: [0, 0],
             selected_atlas: None,
@@ -145,24 +1296,85 @@ impl Default for TemplateApp {
             // sensible default card sizes
             card_width: 535,
             card_height: 752,
+            margin_x: 0,
+            margin_y: 0,
+            card_pitch: None,
+            spacing_x: 0,
+            spacing_y: 0,
+            offset_x: 0,
+            offset_y: 0,
             selected_preset: None,
+            pinned_presets: Vec::new(),
+            user_presets: Vec::new(),
+            card_sets: Vec::new(),
+            current_set: None,
             texture: None,
+            show_grid: false,
+            show_filmstrip: false,
+            card_notes: std::collections::HashMap::new(),
+            show_notes: false,
+            filmstrip_focus: None,
             last_index: None,
             error: None,
+            warning: None,
+            status: None,
+            batch_export: None,
             // regions editor defaults
             regions: Vec::new(),
+            regions_by_atlas: std::collections::HashMap::new(),
+            card_size_by_atlas: std::collections::HashMap::new(),
+            selection_by_atlas: std::collections::HashMap::new(),
+            region_edit: None,
+            paste_region_requested: false,
+            rescale_regions_with_card: false,
+            highlighted_overlap: None,
             drag_start: None,
             drag_current: None,
             pending_region: None,
             new_region_name: String::new(),
             selected_region: None,
+            selected_regions: std::collections::BTreeSet::new(),
+            context_region: None,
             dragging: false,
-            last_pointer_down: false,
-            recent_events: std::collections::VecDeque::with_capacity(256),
-            recent_events_paused: false,
-            event_dump: None,
+            debug_readout: Vec::new(),
+            #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+            clipboard: None,
             pointer_down_on_image: false,
             show_regions_panel: false,
+            regions_panel_width: 260.0,
+            snap_step: 0,
+            overlay_stroke_width: 2.0,
+            overlay_fill_alpha: 40,
+            drag_threshold: 4.0,
+            touch_input: false,
+            texture_filter: TextureFilter::Nearest,
+            view_mode: ViewMode::Fit,
+            show_bleed: false,
+            show_loupe: false,
+            preview_background: PreviewBackground::None,
+            background_color: [255, 0, 255],
+            card_rotation: 0,
+            flip_h: false,
+            flip_v: false,
+            region_sort: RegionSort::Created,
+            zoom: 1.0,
+            pan_offset: egui::Vec2::ZERO,
+            center_on_region: false,
+            window_size: [400.0, 300.0],
+            window_pos: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            window_title: String::new(),
+            key_repeat_delay: 0.4,
+            key_repeat_rate: 12.0,
+            skip_empty_cards: false,
+            slideshow_interval: 1.0,
+            atlas_jobs: AtlasJobs::default(),
+            previews: PreviewCache::default(),
+            history: EditHistory::default(),
+            inputs: TextInputs::default(),
+            prompts: Prompts::default(),
+            measure: MeasureTool::default(),
+            auto_advance: AutoAdvance::default(),
         }
     }
 }
@@ -181,124 +1393,2811 @@ impl TemplateApp {
             Default::default()
         };
 
-        // Try loading atlas file from assets path
-        if let Err(e) = this.load_atlas(Path::new(ATLAS_PATH)) {
-            this.error = Some(format!("Failed to load atlas '{}': {}", ATLAS_PATH, e));
-        }
+        this.restore_saved_state("saved app state");
 
         // Ensure a preview texture exists for the current index
         this.ensure_texture(&cc.egui_ctx);
 
-        // Set visuals to dark by default
-        cc.egui_ctx.set_visuals(egui::Visuals::dark());
+        // Reopen the window where it was left. Applied as viewport commands because the native
+        // options are built before the saved state can be read.
+        #[cfg(not(target_arch = "wasm32"))]
+        if cc.storage.is_some() {
+            let [w, h] = this.window_size;
+            if w > 0.0 && h > 0.0 {
+                cc.egui_ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(w, h)));
+            }
+            if let Some([x, y]) = this.window_pos {
+                cc.egui_ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(x, y)));
+            }
+        }
+
+        // Set visuals to dark by default
+        cc.egui_ctx.set_visuals(egui::Visuals::dark());
+
+        // On wasm, read ownership confirmation from localStorage if present
+        #[cfg(target_arch = "wasm32")]
+        {
+            if let Some(window) = web_sys::window() {
+                if let Ok(Some(ls)) = window.local_storage() {
+                    if let Ok(Some(val)) = ls.get_item("wotr_helper:ownership_confirmed") {
+                        if val == "1" {
+                            this.ownership_confirmed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        this
+    }
+
+    /// Bring freshly deserialized state (the last session's, or imported settings) into shape and
+    /// reopen its atlas. `source` names it in warnings.
+    fn restore_saved_state(&mut self, source: &str) {
+        // The working region set belongs to the persisted atlas. A legacy flat `regions` list is
+        // kept as is (it was drawn on that atlas); otherwise pick the set, and its selection, from the maps.
+        if self.regions.is_empty()
+            && let Some(key) = &self.atlas_path
+        {
+            self.regions = self.regions_by_atlas.remove(key).unwrap_or_default();
+            (self.selected_region, self.selected_regions) = self.selection_by_atlas.remove(key).unwrap_or_default();
+        }
+
+        // Persisted state may have been edited by hand or written by an older version
+        self.validate_layout(source);
+        self.validate_presets(source);
+        self.validate_selection();
+
+        // Reopen the atlas of the last session, falling back to the bundled one. The web build
+        // cannot reopen picked files, so it always starts with the bundled atlas.
+        #[cfg(not(target_arch = "wasm32"))]
+        let last = self.atlas_path.clone().filter(|p| p != ATLAS_PATH);
+        #[cfg(target_arch = "wasm32")]
+        let last: Option<String> = None;
+        match last {
+            Some(path) if is_url(&path) || Path::new(&path).exists() => {
+                if let Err(e) = self.load_atlas(Path::new(&path)) {
+                    self.error = Some(format!("Failed to load atlas '{path}': {e}"));
+                }
+            }
+            missing => {
+                self.atlas_jobs.missing_atlas = missing;
+                if let Err(e) = self.load_atlas(Path::new(ATLAS_PATH)) {
+                    self.error = Some(format!("Failed to load atlas '{ATLAS_PATH}': {e}"));
+                }
+            }
+        }
+    }
+
+    /// Start loading the atlas at `path`. Problems found up front (an unsupported file type) are
+    /// returned right away; decode errors are reported through `error` once the load finishes.
+    fn load_atlas(&mut self, path: &Path) -> Result<(), String> {
+        let key = path.to_string_lossy().to_string();
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        if is_url(&key) {
+            let url = key.clone();
+            self.load_in_background(key, move || fetch_atlas(&url));
+            return Ok(());
+        }
+        if let Some(ext) = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase())
+            && !supported_extensions().contains(&ext.as_str())
+        {
+            return Err(format!("Unsupported atlas file type '.{ext}' (supported: {})", supported_extensions().join(", ")));
+        }
+        let path = path.to_path_buf();
+        let decode = move || -> AtlasLoadResult {
+            let img = decode_atlas(|| std::fs::File::open(&path).map(std::io::BufReader::new))?;
+            // Card size embedded in the PNG metadata (if any); offered to the user once installed
+            let size = [img.width() as usize, img.height() as usize];
+            let hint = std::fs::File::open(&path)
+                .ok()
+                .and_then(|f| crate::png_meta::card_size_hint(std::io::BufReader::new(f), size));
+            Ok((img, hint))
+        };
+
+        // Decoding a large atlas takes a while, so on native it runs on a worker thread and `update`
+        // installs the result. Starting another load drops the pending receiver, which supersedes it.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.load_in_background(key, decode);
+            Ok(())
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let (img, hint) = decode()?;
+            self.install_atlas(img, key, hint);
+            Ok(())
+        }
+    }
+
+    /// Run `load` on a worker thread; `poll_atlas_load` installs its result as atlas `key`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_in_background(&mut self, key: String, load: impl FnOnce() -> AtlasLoadResult + Send + 'static) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let start = std::time::Instant::now();
+            let result = load();
+            // the receiver is gone if a newer load replaced this one
+            if tx.send((result, start.elapsed())).is_err() {
+                log::debug!("Discarding superseded atlas load");
+            }
+        });
+        self.atlas_jobs.atlas_loading = Some((key, rx));
+    }
+
+    /// Install a finished background load (if any). While it is pending, keep repainting so the
+    /// result shows up promptly. A path that fails to load is dropped from the recent list.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_atlas_load(&mut self, ctx: &egui::Context) {
+        let Some((key, rx)) = &self.atlas_jobs.atlas_loading else { return };
+        let (result, took) = match rx.try_recv() {
+            Ok(loaded) => loaded,
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(std::time::Duration::from_millis(50));
+                return;
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                (Err("the loader stopped unexpectedly".to_owned()), std::time::Duration::ZERO)
+            }
+        };
+        let key = key.clone();
+        self.atlas_jobs.atlas_loading = None;
+        match result {
+            Ok((img, hint)) => {
+                self.remember_recent_atlas(&key);
+                let sidecar = crate::atlas::read_sidecar(Path::new(&key));
+                self.install_atlas(img, key, hint);
+                if let Some((path, layout)) = sidecar {
+                    self.apply_sidecar(&path, layout);
+                }
+                self.atlas_jobs.atlas_load_time = Some(took);
+                self.error = None;
+            }
+            Err(e) => {
+                self.recent_atlases.retain(|p| *p != key);
+                self.error = Some(format!("Failed to load atlas '{key}': {e}"));
+            }
+        }
+    }
+
+    /// Load sample atlas `i` of `ATLAS_PRESETS`. Its card size is applied once the atlas is
+    /// installed, so the previous atlas keeps the size it was viewed with.
+    fn open_atlas_preset(&mut self, i: usize) {
+        let Some((_, path, w, h)) = ATLAS_PRESETS.get(i) else { return };
+        self.selected_atlas = Some(i);
+        self.atlas_jobs.pending_preset_size = Some(((*path).to_owned(), [*w, *h]));
+
+        // Load the asset: on native we can read directly, on wasm it will request fetch
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Err(e) = self.load_atlas(Path::new(path)) {
+                self.atlas_jobs.pending_preset_size = None;
+                self.error = Some(e);
+            } else {
+                self.error = None;
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            crate::file_picker::request_asset(path);
+        }
+    }
+
+    /// Make a decoded image the current atlas; `key` is its path (or file name when there is none).
+    fn install_atlas(&mut self, img: image::RgbaImage, key: String, hint: Option<[usize; 2]>) {
+        let (w, h) = img.dimensions();
+        self.atlas = Some(std::sync::Arc::new(img));
+        self.atlas_size = [w as usize, h as usize];
+        self.atlas_jobs.atlas_load_time = None;
+        self.previews.empty_cards = None;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.atlas_jobs.empty_cards_count = None; // a count still running is for the previous atlas
+        }
+        self.switch_atlas(key);
+        // A sample atlas brings its card size. The web build knows it by file name only.
+        if let Some((path, [w, h])) = self.atlas_jobs.pending_preset_size.take()
+            && Path::new(&path).file_name() == self.atlas_path.as_deref().and_then(|k| Path::new(k).file_name())
+        {
+            self.set_card_size(w, h, "the sample atlas");
+            self.selected_preset = None;
+        }
+        // Offer the card size from the metadata; applied only after the user confirms
+        self.set_card_size_hint(hint);
+        // Invalidate any existing texture preview; caller should call ensure_texture after
+        self.texture = None;
+        self.previews.atlas_texture = None;
+        self.previews.thumbnails.clear();
+        self.last_index = None;
+    }
+
+    /// Use the card layout from an atlas's sidecar file (see `atlas::read_sidecar`).
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_sidecar(&mut self, path: &Path, layout: GridLayout) {
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |f| f.to_string_lossy().to_string());
+        self.set_card_size(layout.card_width, layout.card_height, &name);
+        self.margin_x = layout.margin_x;
+        self.margin_y = layout.margin_y;
+        self.spacing_x = layout.spacing_x;
+        self.spacing_y = layout.spacing_y;
+        self.card_pitch = None;
+        self.validate_layout(&name);
+        self.selected_preset = None;
+        if self.index > self.max_index() { self.index = self.max_index(); }
+        // the PNG's own hint is moot once the sidecar has set the size
+        self.set_card_size_hint(self.prompts.card_size_hint);
+        self.status = Some(format!("Card layout from {name}: {}×{}", self.card_width, self.card_height));
+    }
+
+    /// Load atlas image from raw bytes (used by the web file picker); `name` identifies it in place of a path
+    fn load_atlas_bytes(&mut self, bytes: &[u8], name: &str) -> Result<(), String> {
+        let (img, hint) = decode_atlas_bytes(bytes)?;
+        // no real path when loading from a blob; the file name stands in for it
+        self.install_atlas(img, name.to_owned(), hint);
+        Ok(())
+    }
+
+    /// Move `path` to the front of the recent atlases list, dropping the oldest entry when full.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn remember_recent_atlas(&mut self, path: &str) {
+        self.recent_atlases.retain(|p| p != path);
+        self.recent_atlases.push_front(path.to_owned());
+        self.recent_atlases.truncate(MAX_RECENT_ATLASES);
+    }
+
+    /// Pick the new location of the atlas `old` that could not be found at startup; its regions
+    /// move along to the new path.
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn locate_missing_atlas(&mut self, old: &str) {
+        let Some(path) = self
+            .file_dialog()
+            .add_filter("Image", supported_extensions())
+            .pick_file()
+            .inspect(|p| self.remember_dialog_dir(p))
+        else {
+            return;
+        };
+        let new = path.to_string_lossy().to_string();
+        if self.atlas_path.as_deref() == Some(old) {
+            // still the current atlas (the fallback did not load): the working regions stay
+            self.atlas_path = Some(new.clone());
+        } else if let Some(regions) = self.regions_by_atlas.remove(old) {
+            self.regions_by_atlas.entry(new.clone()).or_insert(regions);
+            if let Some(selection) = self.selection_by_atlas.remove(old) {
+                self.selection_by_atlas.entry(new.clone()).or_insert(selection);
+            }
+        }
+        self.recent_atlases.retain(|p| p != old);
+        self.atlas_jobs.missing_atlas = None;
+        match self.load_atlas(&path) {
+            Ok(()) => self.error = None,
+            Err(e) => self.error = Some(format!("Failed to load atlas '{new}': {e}")),
+        }
+    }
+
+    /// Start over from the built-in defaults, as on a first run: all settings and regions are
+    /// dropped and the bundled atlas is loaded again. Ownership stays confirmed.
+    fn reset_app(&mut self) {
+        *self = Self { ownership_confirmed: self.ownership_confirmed, ..Self::default() };
+        if let Err(e) = self.load_atlas(Path::new(ATLAS_PATH)) {
+            self.error = Some(format!("Failed to load atlas '{ATLAS_PATH}': {e}"));
+        }
+        self.status = Some("App reset to defaults".to_owned());
+    }
+
+    /// Load an entry of the recent atlases list; an entry that no longer loads is dropped (here or
+    /// when the background load fails).
+    fn open_recent_atlas(&mut self, path: &str) {
+        match self.load_atlas(Path::new(path)) {
+            Ok(()) => self.error = None,
+            Err(e) => {
+                self.recent_atlases.retain(|p| p != path);
+                self.error = Some(format!("Failed to load atlas '{path}': {e}"));
+            }
+        }
+    }
+
+    /// The working set is not serialized directly; copy it, with its selection and the card size,
+    /// to the other atlases' before saving.
+    fn stash_working_regions(&mut self) {
+        if let Some(key) = &self.atlas_path {
+            self.card_size_by_atlas.insert(key.clone(), [self.card_width, self.card_height]);
+            if self.regions.is_empty() {
+                self.regions_by_atlas.remove(key);
+            } else {
+                self.regions_by_atlas.insert(key.clone(), self.regions.clone());
+            }
+            if self.selected_regions.is_empty() {
+                self.selection_by_atlas.remove(key);
+            } else {
+                self.selection_by_atlas.insert(key.clone(), (self.selected_region, self.selected_regions.clone()));
+            }
+        }
+    }
+
+    /// Make `key` the current atlas, stashing the working regions, selection and card size under the
+    /// previous atlas and bringing in the ones saved for the new one (regions empty if none). Reloading
+    /// the same atlas keeps everything as is.
+    fn switch_atlas(&mut self, key: String) {
+        if self.atlas_path.as_deref() == Some(key.as_str()) { return; }
+        self.capture_card_set();
+        if let Some(old) = self.atlas_path.take() {
+            self.card_size_by_atlas.insert(old.clone(), [self.card_width, self.card_height]);
+            let selection = (self.selected_region.take(), std::mem::take(&mut self.selected_regions));
+            if selection.1.is_empty() {
+                self.selection_by_atlas.remove(&old);
+            } else {
+                self.selection_by_atlas.insert(old.clone(), selection);
+            }
+            let regions = std::mem::take(&mut self.regions);
+            if regions.is_empty() {
+                self.regions_by_atlas.remove(&old);
+            } else {
+                self.regions_by_atlas.insert(old, regions);
+            }
+        }
+        self.regions = self.regions_by_atlas.remove(&key).unwrap_or_default();
+        (self.selected_region, self.selected_regions) = self.selection_by_atlas.remove(&key).unwrap_or_default();
+        self.validate_selection();
+        // An atlas opened before comes back with the card size it was last viewed with
+        if let Some(&[w, h]) = self.card_size_by_atlas.get(&key) {
+            self.set_card_size(w, h, "the settings remembered for this atlas");
+            self.selected_preset = None;
+        }
+        self.atlas_path = Some(key);
+        self.current_set = None; // the regions now come from the new atlas
+        // history refers to the previous set
+        self.pending_region = None;
+        self.history.undo_stack.clear();
+        self.history.redo_stack.clear();
+    }
+
+    /// Write the live card size and regions back into the active card set.
+    fn capture_card_set(&mut self) {
+        let Some(set) = self.current_set.and_then(|i| self.card_sets.get_mut(i)) else { return };
+        set.card_width = self.card_width;
+        set.card_height = self.card_height;
+        set.regions.clone_from(&self.regions);
+    }
+
+    /// Make card set `i` active: its card size and regions replace the live ones and the view jumps
+    /// to its first card.
+    fn select_card_set(&mut self, i: usize) {
+        if self.current_set == Some(i) { return; }
+        self.capture_card_set();
+        let Some(set) = self.card_sets.get(i).cloned() else { return };
+        self.current_set = Some(i);
+        self.selected_preset = None;
+        self.set_card_size(set.card_width, set.card_height, &format!("card set '{}'", set.name));
+        self.regions = set.regions;
+        self.index = set.start_index.min(self.max_index());
+        // history and selection refer to the previous set
+        self.clear_selection();
+        self.pending_region = None;
+        self.history.undo_stack.clear();
+        self.history.redo_stack.clear();
+    }
+
+    /// Start a new card set at the current card with the current size and regions.
+    fn add_card_set(&mut self) {
+        self.capture_card_set();
+        self.card_sets.push(CardSet {
+            name: format!("Set {}", self.card_sets.len() + 1),
+            start_index: self.index,
+            card_width: self.card_width,
+            card_height: self.card_height,
+            regions: self.regions.clone(),
+        });
+        self.current_set = Some(self.card_sets.len() - 1);
+    }
+
+    fn card_sets_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Sets:");
+            let mut select = None;
+            for (i, set) in self.card_sets.iter().enumerate() {
+                let label = format!("{} (from {}, {}×{})", set.name, set.start_index, set.card_width, set.card_height);
+                if ui.selectable_label(self.current_set == Some(i), label).clicked() {
+                    select = Some(i);
+                }
+            }
+            if let Some(i) = select { self.select_card_set(i); }
+            if ui.button("➕").on_hover_text("New set starting at the current card, with the current size and regions").clicked() {
+                self.add_card_set();
+            }
+            if let Some(i) = self.current_set.filter(|&i| i < self.card_sets.len()) {
+                ui.separator();
+                let index = self.index;
+                if let Some(set) = self.card_sets.get_mut(i) {
+                    ui.add(egui::TextEdit::singleline(&mut set.name).desired_width(100.0)).on_hover_text("Set name");
+                    if ui.button("Start here").on_hover_text(format!("Make card {index} the first card of this set")).clicked() {
+                        set.start_index = index;
+                    }
+                }
+                if ui.button("🗑").on_hover_text("Delete this set (the current size and regions stay)").clicked() {
+                    self.card_sets.remove(i);
+                    self.current_set = None;
+                }
+            }
+        });
+    }
+
+    /// Set the card size from an external source (file, metadata...), clamping bad values and
+    /// leaving a warning that names `source` when something had to be adjusted.
+    fn set_card_size(&mut self, w: usize, h: usize, source: &str) {
+        self.card_width = w;
+        self.card_height = h;
+        self.validate_card_size(source);
+        self.texture = None;
+        self.last_index = None;
+        if self.index > self.max_index() { self.index = self.max_index(); }
+    }
+
+    /// Clamp the card size into range; the flag reports whether a warning was left.
+    fn validate_card_size(&mut self, source: &str) -> bool {
+        let ([w, h], adjusted) = clamp_card_size(self.card_width, self.card_height);
+        if adjusted {
+            self.warning = Some(format!(
+                "Card size {}×{} from {} is out of range; clamped to {}×{}",
+                self.card_width, self.card_height, source, w, h
+            ));
+            self.card_width = w;
+            self.card_height = h;
+        }
+        adjusted
+    }
+
+    /// Like `validate_card_size`, for the whole grid: margins and gaps are capped at
+    /// `MAX_CARD_SIDE`, the grid offset is kept within ±`MAX_CARD_SIDE` and an unusable card pitch
+    /// is dropped.
+    fn validate_layout(&mut self, source: &str) {
+        let size_adjusted = self.validate_card_size(source);
+        let side = MAX_CARD_SIDE as i32;
+        let gaps = [self.margin_x, self.margin_y, self.spacing_x, self.spacing_y];
+        let offset = [self.offset_x, self.offset_y];
+        let pitch = self.card_pitch.filter(|p| p.iter().all(|v| (1.0..=MAX_CARD_SIDE as f32).contains(v)));
+        [self.margin_x, self.margin_y, self.spacing_x, self.spacing_y] = gaps.map(|v| v.min(MAX_CARD_SIDE));
+        [self.offset_x, self.offset_y] = offset.map(|v| v.clamp(-side, side));
+        let adjusted = gaps != [self.margin_x, self.margin_y, self.spacing_x, self.spacing_y]
+            || offset != [self.offset_x, self.offset_y]
+            || pitch != self.card_pitch;
+        self.card_pitch = pitch;
+        if adjusted {
+            let note = format!("Margins, spacing, pitch or grid offset from {source} were out of range and have been adjusted");
+            self.warning = Some(match self.warning.take() {
+                Some(size) if size_adjusted => format!("{size}. {note}"),
+                _ => note,
+            });
+        }
+    }
+
+    fn set_card_size_hint(&mut self, hint: Option<[usize; 2]>) {
+        // Nothing to ask about if the metadata matches what is already configured
+        self.prompts.card_size_hint = hint.filter(|s| *s != [self.card_width, self.card_height]);
+    }
+
+    /// The card grid as currently configured.
+    fn grid_layout(&self) -> GridLayout {
+        GridLayout {
+            atlas_size: self.atlas_size,
+            card_width: self.card_width,
+            card_height: self.card_height,
+            margin_x: self.margin_x,
+            margin_y: self.margin_y,
+            spacing_x: self.spacing_x,
+            spacing_y: self.spacing_y,
+            offset_x: self.offset_x,
+            offset_y: self.offset_y,
+            card_pitch: self.card_pitch,
+        }
+    }
+
+    fn cols(&self) -> usize {
+        self.grid_layout().cols()
+    }
+
+    fn rows(&self) -> usize {
+        self.grid_layout().rows()
+    }
+
+    /// Atlas pixels to the right of / below the last whole column / row of cards. Anything beyond a
+    /// trailing margin as wide as the leading one usually means the card size is off.
+    fn leftover_pixels(&self) -> [usize; 2] {
+        let (cols, rows) = (self.cols(), self.rows());
+        let [x, y] = self.card_origin(cols.saturating_sub(1), rows.saturating_sub(1));
+        [
+            if cols == 0 { self.atlas_size[0] } else { self.atlas_size[0].saturating_sub(x + self.card_width) },
+            if rows == 0 { self.atlas_size[1] } else { self.atlas_size[1].saturating_sub(y + self.card_height) },
+        ]
+    }
+
+    /// Atlas pixel position of the top-left corner of the card at `col`, `row`.
+    fn card_origin(&self, col: usize, row: usize) -> [usize; 2] {
+        self.grid_layout().card_origin(col, row)
+    }
+
+    /// The current card's rectangle in the atlas as `[x, y, width, height]`.
+    fn card_cell(&self) -> [usize; 4] {
+        let cols = self.cols().max(1);
+        let [x, y] = self.card_origin(self.index % cols, self.index / cols);
+        [x, y, self.card_width, self.card_height]
+    }
+
+    /// Why there is no card to preview, pointing at the card size when not even one card fits.
+    fn no_preview_reason(&self) -> String {
+        if self.atlas.is_none() {
+            return "No atlas loaded.".to_owned();
+        }
+        let [aw, ah] = self.atlas_size;
+        let (w, h) = (self.card_width, self.card_height);
+        if w > aw || h > ah {
+            return format!("Card size ({w}×{h}) is larger than the atlas ({aw}×{ah}). Reduce the card width/height.");
+        }
+        if self.cols() == 0 || self.rows() == 0 {
+            return format!(
+                "No {w}×{h} card fits in the {aw}×{ah} atlas with a {}×{} px margin. Reduce the margin or the card size.",
+                self.margin_x, self.margin_y
+            );
+        }
+        format!("No card at index {} (the last one is {}).", self.index, self.max_index())
+    }
+
+    fn max_index(&self) -> usize {
+        self.grid_layout().max_index()
+    }
+
+    /// PageUp/PageDown jump a full row, Home/End go to the first/last card. Like the Left/Right
+    /// repeat below, ignored while a text field has focus.
+    fn handle_nav_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() { return; }
+        let (page_up, page_down, home, end) = ctx.input_mut(|i| (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::PageUp),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::PageDown),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Home),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::End),
+        ));
+        let row = self.cols().max(1);
+        let max = self.max_index();
+        if page_up { self.index = self.index.saturating_sub(row); }
+        if page_down { self.index = (self.index + row).min(max); }
+        if home { self.index = 0; }
+        if end { self.index = max; }
+    }
+
+    /// Add the drawn region under the name typed for it (made unique), selecting it.
+    fn add_pending_region(&mut self) {
+        let Some(rect) = self.pending_region.take() else { return };
+        let name = self.unique_region_name(&self.new_region_name);
+        if name != self.new_region_name.trim() && !self.new_region_name.trim().is_empty() {
+            self.status = Some(format!("A region named '{}' exists; added as '{name}'", self.new_region_name.trim()));
+        }
+        self.push_undo();
+        self.regions.push(Region::new(name, rect));
+        self.select_only(self.regions.len() - 1);
+        self.new_region_name.clear();
+    }
+
+    fn cancel_pending_region(&mut self) {
+        self.pending_region = None;
+        self.new_region_name.clear();
+    }
+
+    /// Enter adds the pending region and Escape discards it while no text field has focus (the name
+    /// field handles both keys itself).
+    fn handle_pending_region_keys(&mut self, ctx: &egui::Context) {
+        if self.pending_region.is_none() || ctx.wants_keyboard_input() { return; }
+        let (enter, escape) = ctx.input_mut(|i| (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+        ));
+        if enter { self.add_pending_region(); }
+        if escape { self.cancel_pending_region(); }
+    }
+
+    /// Whether every pixel of card `index` is fully transparent (a gap in the atlas).
+    fn is_card_empty(&self, index: usize) -> bool {
+        self.atlas.as_ref().is_some_and(|atlas| AtlasGrid::new(atlas, self.grid_layout()).is_card_empty(index))
+    }
+
+    /// The card `dir` (±1) steps away from the current one, passing over empty cards when
+    /// `skip_empty_cards` is on. Stays on the current card if there is none that way.
+    fn step_index(&self, dir: i64) -> usize {
+        let max = self.max_index() as i64;
+        let mut i = self.index as i64;
+        loop {
+            i += dir;
+            if i < 0 || i > max { return self.index; }
+            if !self.skip_empty_cards || !self.is_card_empty(i as usize) { return i as usize; }
+        }
+    }
+
+    /// Recount the empty cards after the atlas or its layout changed. The count reads the whole
+    /// atlas, so natively it runs on a worker thread, one at a time: while the layout is still being
+    /// edited, only the layout current when the last count finishes gets counted next.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn refresh_empty_cards(&mut self, ctx: &egui::Context) {
+        if let Some((layout, rx)) = &self.atlas_jobs.empty_cards_count {
+            match rx.try_recv() {
+                Ok(count) => self.previews.empty_cards = Some((*layout, count)),
+                Err(std::sync::mpsc::TryRecvError::Empty) => {
+                    ctx.request_repaint_after(std::time::Duration::from_millis(50));
+                    return;
+                }
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => log::warn!("Counting the empty cards stopped unexpectedly"),
+            }
+            self.atlas_jobs.empty_cards_count = None;
+        }
+        let layout = self.grid_layout();
+        let Some(atlas) = &self.atlas else { return };
+        if self.previews.empty_cards.is_some_and(|(l, _)| l == layout) { return; }
+        let (tx, rx) = std::sync::mpsc::channel();
+        let atlas = std::sync::Arc::clone(atlas);
+        std::thread::spawn(move || {
+            // the receiver is gone if the atlas was replaced meanwhile
+            if tx.send(AtlasGrid::new(&atlas, layout).empty_cards()).is_err() {
+                log::debug!("Discarding empty card count of a replaced atlas");
+            }
+        });
+        self.atlas_jobs.empty_cards_count = Some((layout, rx));
+        ctx.request_repaint_after(std::time::Duration::from_millis(50));
+    }
+
+    /// Recount the empty cards once the atlas or its layout has not changed for
+    /// `EMPTY_CARDS_SETTLE` (the web build has no threads to count them on).
+    #[cfg(target_arch = "wasm32")]
+    fn refresh_empty_cards(&mut self, ctx: &egui::Context) {
+        let layout = self.grid_layout();
+        let Some(atlas) = &self.atlas else { return };
+        if self.previews.empty_cards.is_some_and(|(l, _)| l == layout) { return; }
+        let now = ctx.input(|i| i.time);
+        match self.atlas_jobs.empty_cards_since {
+            Some((l, since)) if l == layout && now - since >= EMPTY_CARDS_SETTLE.as_secs_f64() => {
+                self.previews.empty_cards = Some((layout, AtlasGrid::new(atlas, layout).empty_cards()));
+                self.atlas_jobs.empty_cards_since = None;
+            }
+            Some((l, _)) if l == layout => ctx.request_repaint_after(EMPTY_CARDS_SETTLE),
+            _ => {
+                self.atlas_jobs.empty_cards_since = Some((layout, now));
+                ctx.request_repaint_after(EMPTY_CARDS_SETTLE);
+            }
+        }
+    }
+
+    /// While the slideshow plays, advance to the next card every `slideshow_interval` seconds,
+    /// wrapping around to the first card after the last.
+    fn step_slideshow(&mut self, ctx: &egui::Context) {
+        let Some(next) = self.auto_advance.slideshow_next else { return };
+        let now = ctx.input(|i| i.time);
+        let next = if now >= next {
+            self.index = if self.index >= self.max_index() { 0 } else { self.index + 1 };
+            now + f64::from(self.slideshow_interval.max(0.05))
+        } else {
+            next
+        };
+        self.auto_advance.slideshow_next = Some(next);
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(next - now));
+    }
+
+    /// Arrow keys move the selected regions by one card pixel (ten with Shift), as a group that stays
+    /// on the card. Holding or tapping an arrow is a single undo step until all arrows are released.
+    fn handle_region_nudge(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() { return; }
+        let (mut dx, mut dy) = (0i64, 0i64);
+        let any_down = ctx.input(|i| {
+            for ev in &i.events {
+                if let egui::Event::Key { key, pressed: true, modifiers, .. } = ev {
+                    let step = if modifiers.shift { 10 } else { 1 };
+                    match key {
+                        egui::Key::ArrowLeft => dx -= step,
+                        egui::Key::ArrowRight => dx += step,
+                        egui::Key::ArrowUp => dy -= step,
+                        egui::Key::ArrowDown => dy += step,
+                        _ => {}
+                    }
+                }
+            }
+            [egui::Key::ArrowLeft, egui::Key::ArrowRight, egui::Key::ArrowUp, egui::Key::ArrowDown]
+                .iter()
+                .any(|k| i.key_down(*k))
+        });
+
+        if dx != 0 || dy != 0 {
+            let selected: Vec<&Region> = self.selected_regions.iter().filter_map(|i| self.regions.get(*i)).filter(|r| !r.locked).collect();
+            // Limit the move so no selected region leaves the card
+            let min_x = selected.iter().map(|r| r.x).min().unwrap_or(0) as i64;
+            let min_y = selected.iter().map(|r| r.y).min().unwrap_or(0) as i64;
+            let max_right = selected.iter().map(|r| r.x + r.width).max().unwrap_or(0) as i64;
+            let max_bottom = selected.iter().map(|r| r.y + r.height).max().unwrap_or(0) as i64;
+            let dx = dx.clamp(-min_x, (self.card_width as i64 - max_right).max(0));
+            let dy = dy.clamp(-min_y, (self.card_height as i64 - max_bottom).max(0));
+            if dx != 0 || dy != 0 {
+                if self.history.nudge_snapshot.is_none() { self.history.nudge_snapshot = Some(self.regions.clone()); }
+                for i in &self.selected_regions {
+                    if let Some(r) = self.regions.get_mut(*i).filter(|r| !r.locked) {
+                        r.x = (r.x as i64 + dx) as usize;
+                        r.y = (r.y as i64 + dy) as usize;
+                    }
+                }
+            }
+        }
+
+        if !any_down && let Some(snapshot) = self.history.nudge_snapshot.take() {
+            self.push_undo_snapshot(snapshot);
+        }
+    }
+
+    /// Step `index` while Left/Right is held: one step on press, then after `key_repeat_delay`
+    /// keep stepping at `key_repeat_rate` per second. Stops at the ends of the index range.
+    fn handle_nav_key_repeat(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            self.auto_advance.nav_repeat = None;
+            return;
+        }
+        let (left, right, now) = ctx.input(|i| (i.key_down(egui::Key::ArrowLeft), i.key_down(egui::Key::ArrowRight), i.time));
+        let dir: i64 = match (left, right) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => {
+                self.auto_advance.nav_repeat = None;
+                return;
+            }
+        };
+
+        let next_step = match self.auto_advance.nav_repeat {
+            Some((held, next)) if held == dir => {
+                if now < next {
+                    ctx.request_repaint_after(std::time::Duration::from_secs_f64(next - now));
+                    return;
+                }
+                now + 1.0 / f64::from(self.key_repeat_rate.max(1.0))
+            }
+            // newly pressed (or direction changed): step once, then wait for the initial delay
+            _ => now + f64::from(self.key_repeat_delay.max(0.0)),
+        };
+
+        self.index = self.step_index(dir);
+        self.auto_advance.nav_repeat = Some((dir, next_step));
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(next_step - now));
+    }
+
+    /// Cut card `index` out of the atlas as a standalone image (used for the preview and exports).
+    fn make_card_rgba(&self, index: usize) -> Option<image::RgbaImage> {
+        AtlasGrid::new(self.atlas.as_ref()?, self.grid_layout()).card(index)
+    }
+
+    fn card_transformed(&self) -> bool {
+        matches!(self.card_rotation, 90 | 180 | 270) || self.flip_h || self.flip_v
+    }
+
+    /// Card `index` in the chosen view orientation (what the preview shows and exports write).
+    fn make_oriented_card(&self, index: usize) -> Option<image::RgbaImage> {
+        let img = self.make_card_rgba(index)?;
+        Some(self.orient(img))
+    }
+
+    fn orient(&self, img: image::RgbaImage) -> image::RgbaImage {
+        if !self.card_transformed() { return img; }
+        transform_card(img, self.card_rotation, self.flip_h, self.flip_v)
+    }
+
+    /// Map a pixel of the oriented preview (`w`x`h` being the unoriented card size) back to the card.
+    fn unorient_px(&self, (dx, dy): (usize, usize), [w, h]: [usize; 2]) -> (usize, usize) {
+        let (x, y) = match self.card_rotation {
+            90 => (dy, h - 1 - dx),
+            180 => (w - 1 - dx, h - 1 - dy),
+            270 => (w - 1 - dy, dx),
+            _ => (dx, dy),
+        };
+        (if self.flip_h { w - 1 - x } else { x }, if self.flip_v { h - 1 - y } else { y })
+    }
+
+    fn ensure_texture(&mut self, ctx: &egui::Context) {
+        if self.last_index == Some(self.index) { return; }
+        self.texture = None;
+        self.last_index = None;
+
+        if let Some(img) = self.make_oriented_card(self.index) {
+            let img = ColorImage::from_rgba_unmultiplied([img.width() as usize, img.height() as usize], img.as_raw());
+            let tex = ctx.load_texture(
+                "card_preview",
+                img,
+                self.texture_filter.options(),
+            );
+            self.texture = Some(tex);
+            self.last_index = Some(self.index);
+        }
+        self.previews.bleed_texture = self.show_bleed.then(|| self.make_bleed_card(self.index)).flatten().map(|img| {
+            let img = ColorImage::from_rgba_unmultiplied([img.width() as usize, img.height() as usize], img.as_raw());
+            ctx.load_texture("card_bleed", img, self.texture_filter.options())
+        });
+    }
+
+    /// Card `index` with `BLEED_PX` of its surroundings on every side, oriented like the card.
+    /// Parts beyond the atlas edge stay transparent.
+    fn make_bleed_card(&self, index: usize) -> Option<image::RgbaImage> {
+        let atlas = self.atlas.as_deref()?;
+        let cols = self.cols();
+        if cols == 0 || index > self.max_index() { return None; }
+        let [x, y] = self.card_origin(index % cols, index / cols);
+        let (x0, y0) = (x.saturating_sub(BLEED_PX), y.saturating_sub(BLEED_PX));
+        let x1 = (x + self.card_width + BLEED_PX).min(self.atlas_size[0]);
+        let y1 = (y + self.card_height + BLEED_PX).min(self.atlas_size[1]);
+        let crop = image::imageops::crop_imm(atlas, x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32);
+        let side = 2 * BLEED_PX as u32;
+        let mut img = image::RgbaImage::new(self.card_width as u32 + side, self.card_height as u32 + side);
+        image::imageops::replace(&mut img, &*crop, (x0 + BLEED_PX - x) as i64, (y0 + BLEED_PX - y) as i64);
+        Some(self.orient(img))
+    }
+
+    /// A native file dialog that opens where the previous one left off.
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn file_dialog(&self) -> FileDialog {
+        match &self.last_dir {
+            Some(dir) => FileDialog::new().set_directory(dir),
+            None => FileDialog::new(),
+        }
+    }
+
+    /// Remember the folder of a picked file (or a picked folder itself) for the next dialog.
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn remember_dialog_dir(&mut self, picked: &std::path::Path) {
+        let dir = if picked.is_dir() { Some(picked) } else { picked.parent() };
+        if let Some(dir) = dir.filter(|d| !d.as_os_str().is_empty()) {
+            self.last_dir = Some(dir.to_string_lossy().into_owned());
+        }
+    }
+
+    /// Fill `rect` (the card on screen) with the chosen preview background; `painter` clips it to
+    /// the visible part. Checkerboard squares are anchored to the card so they move with it.
+    fn paint_preview_background(&self, painter: &egui::Painter, rect: egui::Rect) {
+        let fill = match self.preview_background {
+            PreviewBackground::None => return,
+            PreviewBackground::Checkerboard => egui::Color32::from_gray(204),
+            PreviewBackground::White => egui::Color32::WHITE,
+            PreviewBackground::Black => egui::Color32::BLACK,
+            PreviewBackground::Custom => {
+                let [r, g, b] = self.background_color;
+                egui::Color32::from_rgb(r, g, b)
+            }
+        };
+        painter.rect_filled(rect, 0.0, fill);
+        if self.preview_background != PreviewBackground::Checkerboard { return; }
+
+        // Only the dark squares in the visible part are drawn over the light fill
+        let visible = painter.clip_rect().intersect(rect);
+        if !visible.is_positive() { return; }
+        let first = ((visible.min - rect.min) / CHECKER_SIZE).floor();
+        let last = ((visible.max - rect.min) / CHECKER_SIZE).ceil();
+        let dark = egui::Color32::from_gray(153);
+        for row in first.y as i64..last.y as i64 {
+            for col in first.x as i64..last.x as i64 {
+                if (row + col) % 2 == 0 { continue; }
+                let min = rect.min + egui::vec2(col as f32, row as f32) * CHECKER_SIZE;
+                painter.rect_filled(egui::Rect::from_min_size(min, egui::Vec2::splat(CHECKER_SIZE)), 0.0, dark);
+            }
+        }
+    }
+
+    /// Pick an atlas image and load it (native dialog, or the async picker on web).
+    fn open_atlas_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(path) = self.file_dialog().add_filter("Image", supported_extensions()).pick_file().inspect(|p| self.remember_dialog_dir(p)) {
+                match self.load_atlas(&path) {
+                    Ok(()) => self.error = None,
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            crate::file_picker::open_image_picker();
+        }
+    }
+
+    /// Re-read the current atlas from disk.
+    fn reload_atlas(&mut self) {
+        if let Some(p) = self.atlas_path.clone() {
+            if let Err(e) = self.load_atlas(Path::new(&p)) {
+                self.error = Some(e);
+            } else {
+                self.error = None;
+            }
+        }
+    }
+
+    /// Keep the file watch in line with "Watch file" and the current atlas, and reload the atlas
+    /// once its file has stopped changing for `WATCH_DEBOUNCE`. The reload keeps the card index
+    /// and the regions, like "Reload".
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn update_atlas_watch(&mut self, ctx: &egui::Context) {
+        let wanted = self.atlas_path.clone().filter(|p| self.watch_atlas && !is_url(p) && self.atlas.is_some());
+        if self.atlas_jobs.atlas_watch.as_ref().map(|w| &w.path) != wanted.as_ref() {
+            self.atlas_jobs.atlas_watch = None;
+            if let Some(path) = wanted {
+                match AtlasWatch::new(&path, ctx) {
+                    Ok(watch) => self.atlas_jobs.atlas_watch = Some(watch),
+                    Err(e) => {
+                        self.watch_atlas = false;
+                        self.error = Some(format!("Cannot watch '{path}': {e}"));
+                    }
+                }
+            }
+        }
+        let Some(watch) = &mut self.atlas_jobs.atlas_watch else { return };
+        if watch.changes.try_iter().count() > 0 {
+            watch.changed_at = Some(std::time::Instant::now());
+        }
+        let Some(changed_at) = watch.changed_at else { return };
+        let quiet = changed_at.elapsed();
+        if quiet < WATCH_DEBOUNCE || self.atlas_jobs.atlas_loading.is_some() {
+            ctx.request_repaint_after(WATCH_DEBOUNCE.saturating_sub(quiet));
+            return;
+        }
+        watch.changed_at = None;
+        self.reload_atlas();
+        self.status = Some("Atlas changed on disk; reloaded".to_owned());
+    }
+
+    /// Save the regions (plus the card size they were drawn for) to a JSON file.
+    fn save_regions_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(path) = self.file_dialog().add_filter("JSON", &["json"]).save_file().inspect(|p| self.remember_dialog_dir(p)) {
+                // The dialog already asked about overwriting; ask again only if the file is a regions
+                // file in another format, which this save would silently convert
+                let existing = std::fs::read_to_string(&path).ok().and_then(|s| regions_file_format(&s));
+                let writing = regions_file_format_name(false, self.save_coordinate_space);
+                match existing {
+                    Some(format) if format != writing => self.prompts.pending_regions_overwrite = Some((path, format)),
+                    _ => self.write_regions_file(&path),
+                }
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            match self.regions_file_json() {
+                Ok(json) => crate::file_picker::download_bytes(json.as_bytes(), "regions.json", "application/json"),
+                Err(e) => self.error = Some(format!("Failed to save regions file: {e}")),
+            }
+        }
+    }
+
+    /// Write the regions in the current format (see `save_regions_dialog`).
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn write_regions_file(&mut self, path: &std::path::Path) {
+        let result = self.regions_file_json().and_then(|s| std::fs::write(path, s).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            self.error = Some(format!("Failed to save regions file: {e}"));
+        }
+    }
+
+    /// Contents of a regions file: the regions in the chosen coordinate space plus the card size
+    /// they were drawn for.
+    fn regions_file_json(&self) -> Result<String, String> {
+        let mut regions = self.regions.clone();
+        if self.save_coordinate_space == CoordinateSpace::Atlas {
+            let [cx, cy, _, _] = self.card_cell();
+            for r in &mut regions {
+                r.x += cx;
+                r.y += cy;
+            }
+        }
+        let file = RegionsFile {
+            version: REGIONS_FILE_VERSION,
+            image_size: [self.card_width, self.card_height],
+            coordinate_space: self.save_coordinate_space,
+            regions,
+        };
+        serde_json::to_string_pretty(&file).map_err(|e| e.to_string())
+    }
+
+    /// The regions as a Rust constant of `(name, x, y, width, height)` tuples, for pasting into code
+    /// that should not depend on this app or egui.
+    #[cfg(not(target_os = "android"))]
+    fn regions_to_rust(&self) -> String {
+        let mut out = format!(
+            "// Regions of a {}x{} card, generated by wotr_helper\npub const REGIONS: &[(&str, usize, usize, usize, usize)] = &[\n",
+            self.card_width, self.card_height
+        );
+        for r in &self.regions {
+            // Debug formatting of a str is a valid, escaped Rust string literal
+            out += &format!("    ({:?}, {}, {}, {}, {}),\n", r.name, r.x, r.y, r.width, r.height);
+        }
+        out += "];\n";
+        out
+    }
+
+    fn export_regions_rust_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(path) = self.file_dialog().add_filter("Rust", &["rs"]).set_file_name("regions.rs").save_file().inspect(|p| self.remember_dialog_dir(p))
+                && let Err(e) = std::fs::write(&path, self.regions_to_rust())
+            {
+                self.error = Some(format!("Failed to write '{}': {e}", path.display()));
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        crate::file_picker::download_bytes(self.regions_to_rust().as_bytes(), "regions.rs", "text/plain");
+    }
+
+    /// The regions as an SVG the size of the card: each region is a rectangle in its overlay color
+    /// labeled with its name. With `embed_card` the current card is the background, as a PNG
+    /// `data:` URL, so the file stands on its own.
+    fn regions_to_svg(&self, embed_card: bool) -> String {
+        let (w, h) = (self.card_width, self.card_height);
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n"
+        );
+        if let Some(png) = self.make_card_rgba(self.index).filter(|_| embed_card).and_then(|card| encode_png(&card).ok()) {
+            out += &format!("  <image x=\"0\" y=\"0\" width=\"{w}\" height=\"{h}\" href=\"data:image/png;base64,{}\"/>\n", base64(&png));
+        }
+        out += "  <g font-family=\"sans-serif\" font-size=\"14\">\n";
+        for r in &self.regions {
+            let [cr, cg, cb, ca] = r.color.unwrap_or(DEFAULT_REGION_COLOR);
+            let color = format!("rgb({cr},{cg},{cb})");
+            let opacity = f32::from(ca) / 255.0;
+            let name = xml_escape(&r.name);
+            out += &format!(
+                "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-opacity=\"{opacity:.2}\" stroke-width=\"2\"><title>{name}</title></rect>\n",
+                r.x, r.y, r.width, r.height
+            );
+            out += &format!("    <text x=\"{}\" y=\"{}\" fill=\"{color}\">{name}</text>\n", r.x + 3, r.y + 15);
+        }
+        out += "  </g>\n</svg>\n";
+        out
+    }
+
+    fn export_svg_dialog(&mut self) {
+        let svg = self.regions_to_svg(self.svg_embed_card);
+
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(path) = self.file_dialog().add_filter("SVG", &["svg"]).set_file_name("regions.svg").save_file().inspect(|p| self.remember_dialog_dir(p))
+                && let Err(e) = std::fs::write(&path, svg)
+            {
+                self.error = Some(format!("Failed to write '{}': {e}", path.display()));
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            log::warn!("Cannot export an SVG of {} bytes on Android", svg.len());
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        crate::file_picker::download_bytes(svg.as_bytes(), "regions.svg", "image/svg+xml");
+    }
+
+    /// Describe every card of the atlas with the regions; `shared` lists the regions once instead
+    /// of repeating them for each card.
+    #[cfg(not(target_os = "android"))]
+    fn atlas_manifest(&self, shared: bool) -> AtlasManifest {
+        let atlas = self.atlas_path.as_deref().map_or_else(String::new, |p| {
+            Path::new(p).file_name().map_or_else(|| p.to_owned(), |f| f.to_string_lossy().into_owned())
+        });
+        let (cols, rows) = (self.cols(), self.rows());
+        let cards = (0..cols * rows)
+            .map(|index| {
+                let [x, y] = self.card_origin(index % cols, index / cols);
+                ManifestCard { index, x, y, regions: (!shared).then(|| self.regions.clone()) }
+            })
+            .collect();
+        AtlasManifest {
+            atlas,
+            card_width: self.card_width,
+            card_height: self.card_height,
+            cols,
+            rows,
+            regions: shared.then(|| self.regions.clone()),
+            cards,
+        }
+    }
+
+    fn export_manifest_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            let Some(path) = self
+                .file_dialog()
+                .add_filter("JSON", &["json"])
+                .set_file_name("manifest.json")
+                .save_file()
+                .inspect(|p| self.remember_dialog_dir(p))
+            else {
+                return;
+            };
+            let result = serde_json::to_string_pretty(&self.atlas_manifest(self.manifest_shared_regions))
+                .map_err(|e| e.to_string())
+                .and_then(|s| std::fs::write(&path, s).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => self.status = Some(format!("Wrote atlas manifest to {}", path.display())),
+                Err(e) => self.error = Some(format!("Failed to write '{}': {e}", path.display())),
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            match serde_json::to_string_pretty(&self.atlas_manifest(self.manifest_shared_regions)) {
+                Ok(json) => crate::file_picker::download_bytes(json.as_bytes(), "manifest.json", "application/json"),
+                Err(e) => self.error = Some(format!("Failed to save atlas manifest: {e}")),
+            }
+        }
+    }
+
+    /// Everything that is persisted between sessions (settings, presets, card sets, regions of
+    /// every atlas, recent atlases) as JSON; the atlas image and other runtime state are left out.
+    #[cfg(not(target_os = "android"))]
+    fn settings_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    /// App state from exported settings. The window placement and ownership confirmation belong
+    /// to this machine and are kept; the card layout and presets are brought into range, with a
+    /// warning when something had to be adjusted.
+    #[cfg(not(target_os = "android"))]
+    fn settings_from_json(&self, json: &str) -> Result<Self, String> {
+        let imported: Self = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let mut imported = Self {
+            ownership_confirmed: self.ownership_confirmed,
+            window_size: self.window_size,
+            window_pos: self.window_pos,
+            ..imported
+        };
+        imported.validate_layout("imported settings");
+        imported.validate_presets("imported settings");
+        Ok(imported)
+    }
+
+    fn export_settings_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            let Some(path) = self
+                .file_dialog()
+                .add_filter("JSON", &["json"])
+                .set_file_name("wotr_helper_settings.json")
+                .save_file()
+                .inspect(|p| self.remember_dialog_dir(p))
+            else {
+                return;
+            };
+            self.stash_working_regions();
+            let result = self.settings_json().and_then(|s| std::fs::write(&path, s).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => self.status = Some(format!("Exported settings to {}", path.display())),
+                Err(e) => self.error = Some(format!("Failed to write '{}': {e}", path.display())),
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.stash_working_regions();
+            match self.settings_json() {
+                Ok(json) => crate::file_picker::download_bytes(json.as_bytes(), "wotr_helper_settings.json", "application/json"),
+                Err(e) => self.error = Some(format!("Failed to export settings: {e}")),
+            }
+        }
+    }
+
+    /// Replace the whole app state with exported settings and reopen their atlas.
+    fn import_settings_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            let Some(path) = self.file_dialog().add_filter("JSON", &["json"]).pick_file().inspect(|p| self.remember_dialog_dir(p)) else {
+                return;
+            };
+            match std::fs::read_to_string(&path) {
+                Ok(s) => self.apply_settings(&s, &path.display().to_string()),
+                Err(e) => self.error = Some(format!("Failed to import settings from '{}': {e}", path.display())),
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        self.pick_web_import(WebImport::Settings, ".json");
+    }
+
+    /// Replace the whole app state with exported settings read from `source`.
+    #[cfg(not(target_os = "android"))]
+    fn apply_settings(&mut self, json: &str, source: &str) {
+        match self.settings_from_json(json) {
+            Ok(imported) => {
+                *self = imported;
+                self.restore_saved_state("imported settings");
+                self.status = Some(format!("Imported settings from {source}"));
+            }
+            Err(e) => self.error = Some(format!("Failed to import settings from '{source}': {e}")),
+        }
+    }
+
+    /// Open the browser's file picker for a text file matching `accept`; `update` imports it as
+    /// `kind` once it has been read.
+    #[cfg(target_arch = "wasm32")]
+    fn pick_web_import(&mut self, kind: WebImport, accept: &str) {
+        self.prompts.web_import = Some(kind);
+        crate::file_picker::open_text_picker(accept);
+    }
+
+    /// Import a text file picked in the browser as what it was picked for.
+    #[cfg(target_arch = "wasm32")]
+    fn apply_web_import(&mut self, text: &str) {
+        match self.prompts.web_import.take() {
+            Some(WebImport::Regions) => self.apply_regions_file(text),
+            Some(WebImport::RegionsCsv) => self.apply_regions_csv(text, "the CSV file"),
+            Some(WebImport::TexturePacker) => self.apply_texturepacker(text, "the atlas JSON"),
+            Some(WebImport::Settings) => self.apply_settings(text, "the settings file"),
+            None => log::warn!("Ignoring a picked file nothing asked for"),
+        }
+    }
+
+    /// Load regions from a JSON file written by `save_regions_dialog` (or the older bare-list format).
+    fn load_regions_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(path) = self.file_dialog().add_filter("JSON", &["json"]).pick_file().inspect(|p| self.remember_dialog_dir(p)) {
+                match std::fs::read_to_string(&path) {
+                    Ok(s) => self.apply_regions_file(&s),
+                    Err(e) => { self.error = Some(format!("Failed to read regions file: {e}")); },
+                }
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        // The browser hands the file over asynchronously; `update` picks it up
+        #[cfg(target_arch = "wasm32")]
+        self.pick_web_import(WebImport::Regions, ".json");
+    }
+
+    /// Replace the regions with the contents of a regions file (see `load_regions_dialog`).
+    fn apply_regions_file(&mut self, s: &str) {
+        // Objects are versioned regions files, arrays the old bare list. The version is read on its
+        // own first, so a file this version cannot parse is reported as such rather than as an
+        // unknown format.
+        let value: serde_json::Value = match serde_json::from_str(s) {
+            Ok(value) => value,
+            Err(e) => {
+                self.error = Some(format!("Failed to parse regions file: {e}"));
+                return;
+            }
+        };
+        let version = value.as_object().map(|fields| fields.get("version").and_then(serde_json::Value::as_u64).unwrap_or(0));
+        let f = match version {
+            Some(v) if v > u64::from(REGIONS_FILE_VERSION) => {
+                self.error = Some(format!("Regions file version {v} is newer than this app understands (up to {REGIONS_FILE_VERSION})"));
+                return;
+            }
+            // 0 (no version field) and 1 share the object layout
+            Some(_) => match serde_json::from_value::<RegionsFile>(value) {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    self.error = Some(format!("Failed to parse regions file: {e}"));
+                    return;
+                }
+            },
+            None if value.is_array() => None,
+            None => {
+                self.error = Some("Failed to parse regions file: unknown format".to_owned());
+                return;
+            }
+        };
+
+        if let Some(f) = f {
+            self.push_undo();
+            self.clear_selection();
+            // Update card size to match saved file (also invalidates the preview)
+            self.set_card_size(f.image_size[0], f.image_size[1], "regions file");
+            self.selected_preset = None;
+            self.regions = match f.coordinate_space {
+                CoordinateSpace::Card => f.regions,
+                // atlas pixels are mapped onto the current card; regions off it are dropped
+                CoordinateSpace::Atlas => {
+                    let (regions, skipped) = regions_to_card_space(f.regions, self.card_cell());
+                    if skipped > 0 {
+                        self.warning = Some(format!("Skipped {skipped} regions outside card {}", self.index));
+                    }
+                    regions
+                }
+            };
+        } else {
+            // Old format: a bare list of regions in card pixels
+            match serde_json::from_str::<Vec<Region>>(s) {
+                Ok(v) => {
+                    self.push_undo();
+                    self.regions = v;
+                    self.clear_selection();
+                }
+                Err(e) => self.error = Some(format!("Failed to parse regions file: {e}")),
+            }
+        }
+    }
+
+    /// Replace the regions with rows from a `name,x,y,width,height` CSV file. Valid rows are
+    /// imported even when others are malformed; those are listed in the error message. A file
+    /// without any valid row leaves the regions alone.
+    fn load_regions_csv_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(path) = self.file_dialog().add_filter("CSV", &["csv"]).pick_file().inspect(|p| self.remember_dialog_dir(p)) {
+                match std::fs::read_to_string(&path) {
+                    Ok(s) => self.apply_regions_csv(&s, &path.display().to_string()),
+                    Err(e) => self.error = Some(format!("Failed to import CSV file '{}': {e}", path.display())),
+                }
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        self.pick_web_import(WebImport::RegionsCsv, ".csv");
+    }
+
+    /// Replace the regions with the rows of CSV text read from `source` (see `load_regions_csv_dialog`).
+    #[cfg(not(target_os = "android"))]
+    fn apply_regions_csv(&mut self, s: &str, source: &str) {
+        match regions_from_csv(s) {
+            Ok((regions, errors)) => {
+                self.push_undo();
+                self.status = Some(format!("Imported {} regions from {source}", regions.len()));
+                self.regions = regions;
+                self.clear_selection();
+                self.error = if errors.is_empty() {
+                    None
+                } else {
+                    Some(format!("Skipped {} malformed CSV rows: {}", errors.len(), errors.join("; ")))
+                };
+            }
+            Err(e) => self.error = Some(format!("Failed to import CSV file '{source}': {e}")),
+        }
+    }
+
+    /// Pick a `TexturePacker` "JSON (Hash)" file and add its frames that fall inside the current card
+    /// cell as regions (appended to the existing ones).
+    fn import_texturepacker_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            let Some(path) = self.file_dialog().add_filter("JSON", &["json"]).pick_file().inspect(|p| self.remember_dialog_dir(p)) else { return };
+            let json = match std::fs::read_to_string(&path) {
+                Ok(s) => s,
+                Err(e) => {
+                    self.error = Some(format!("Failed to read '{}': {e}", path.display()));
+                    return;
+                }
+            };
+            self.apply_texturepacker(&json, &path.display().to_string());
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        self.pick_web_import(WebImport::TexturePacker, ".json");
+    }
+
+    /// Add the frames of `TexturePacker` JSON read from `source` that fall inside the current card
+    /// cell (see `import_texturepacker_dialog`).
+    #[cfg(not(target_os = "android"))]
+    fn apply_texturepacker(&mut self, json: &str, source: &str) {
+        match regions_from_texturepacker(json, self.card_cell()) {
+            Ok((imported, skipped)) => {
+                self.push_undo();
+                let count = imported.len();
+                for mut r in imported {
+                    r.name = self.unique_region_name(&r.name);
+                    self.regions.push(r);
+                }
+                self.error = None;
+                self.status = Some(format!(
+                    "Imported {count} frames from {source} into card {}; skipped {skipped} outside the card",
+                    self.index
+                ));
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn run_palette_action(&mut self, action: PaletteAction) {
+        let max = self.max_index();
+        match action {
+            PaletteAction::OpenAtlas => self.open_atlas_dialog(),
+            PaletteAction::ReloadAtlas => self.reload_atlas(),
+            PaletteAction::SaveRegions => self.save_regions_dialog(),
+            PaletteAction::LoadRegions => self.load_regions_dialog(),
+            PaletteAction::ExportCard => self.export_card_dialog(),
+            PaletteAction::ExportAllCards => self.export_all_dialog(),
+            PaletteAction::ExportRegions => self.export_regions_dialog(),
+            PaletteAction::ToggleRegionsPanel => self.show_regions_panel = !self.show_regions_panel,
+            PaletteAction::CenterOnRegion => self.request_center_on_region(),
+            PaletteAction::PrevCard => self.index = self.index.saturating_sub(1),
+            PaletteAction::NextCard => self.index = (self.index + 1).min(max),
+            PaletteAction::FirstCard => self.index = 0,
+            PaletteAction::LastCard => self.index = max,
+            PaletteAction::JumpTo(i) => self.index = i.min(max),
+        }
+    }
+
+    /// Zoom and pan the preview to the selected region on the next frame (it needs the viewport).
+    fn request_center_on_region(&mut self) {
+        if self.selected_region.is_none() {
+            self.status = Some("Select a region to center on".to_owned());
+        } else if self.card_transformed() {
+            self.status = Some("Regions are shown in atlas orientation only; reset rotation and flips to center on one".to_owned());
+        } else {
+            self.show_grid = false;
+            self.center_on_region = true;
+        }
+    }
+
+    /// Searchable list of actions, opened with Ctrl+P. Up/Down move the highlight, Enter runs it,
+    /// Escape closes.
+    fn command_palette_ui(&mut self, ctx: &egui::Context) {
+        let shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::P);
+        if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+            self.prompts.palette_open = !self.prompts.palette_open;
+            self.prompts.palette_query.clear();
+            self.prompts.palette_selected = 0;
+        }
+        if !self.prompts.palette_open { return; }
+
+        let actions = PaletteAction::matching(&self.prompts.palette_query);
+        let (up, down, enter, escape) = ctx.input_mut(|i| (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+        ));
+        if up { self.prompts.palette_selected = self.prompts.palette_selected.saturating_sub(1); }
+        if down { self.prompts.palette_selected += 1; }
+        self.prompts.palette_selected = self.prompts.palette_selected.min(actions.len().saturating_sub(1));
+
+        let mut run = if enter { actions.get(self.prompts.palette_selected).copied() } else { None };
+
+        egui::Window::new("Command palette")
+            .collapsible(false)
+            .resizable(false)
+            .title_bar(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .show(ctx, |ui| {
+                let edit = ui.add(egui::TextEdit::singleline(&mut self.prompts.palette_query).hint_text("Type a command or card index"));
+                edit.request_focus();
+                if edit.changed() { self.prompts.palette_selected = 0; }
+                ui.separator();
+                if actions.is_empty() {
+                    ui.weak("No matching commands");
+                }
+                for (i, action) in actions.iter().enumerate() {
+                    if ui.selectable_label(i == self.prompts.palette_selected, action.label()).clicked() {
+                        run = Some(*action);
+                    }
+                }
+            });
+
+        if let Some(action) = run {
+            self.prompts.palette_open = false;
+            self.run_palette_action(action);
+        } else if escape {
+            self.prompts.palette_open = false;
+        }
+    }
+
+    /// Save the current card as a PNG (native save dialog, or a browser download on web).
+    fn export_card_dialog(&mut self) {
+        let Some(img) = self.make_oriented_card(self.index) else {
+            self.error = Some(format!("No card at index {} to export", self.index));
+            return;
+        };
+        let default_name = format!("card_{}.png", self.index);
+
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(path) = self.file_dialog().add_filter("PNG", &["png"]).set_file_name(&default_name).save_file().inspect(|p| self.remember_dialog_dir(p))
+                && let Err(e) = img.save_with_format(&path, image::ImageFormat::Png)
+            {
+                self.error = Some(format!("Failed to export card: {e}"));
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            log::warn!("Cannot export {default_name} ({}x{}) on Android", img.width(), img.height());
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            match encode_png(&img) {
+                Ok(bytes) => crate::file_picker::download_bytes(&bytes, &default_name, "image/png"),
+                Err(e) => self.error = Some(format!("Failed to export card: {e}")),
+            }
+        }
+    }
+
+    /// Put the current card on the system clipboard as an image. The browser cannot reliably write
+    /// images to the clipboard, so the web build downloads the card as a PNG instead.
+    fn copy_card_to_clipboard(&mut self) {
+        let Some(img) = self.make_oriented_card(self.index) else {
+            self.error = Some(format!("No card at index {} to copy", self.index));
+            return;
+        };
+
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            let (width, height) = (img.width() as usize, img.height() as usize);
+            let data = arboard::ImageData { width, height, bytes: std::borrow::Cow::Owned(img.into_raw()) };
+            // The clipboard is kept: on X11 the copied image is only available while its owner lives
+            let result = match &mut self.clipboard {
+                Some(cb) => cb.set_image(data),
+                None => arboard::Clipboard::new().and_then(|mut cb| {
+                    let r = cb.set_image(data);
+                    self.clipboard = Some(cb);
+                    r
+                }),
+            };
+            match result {
+                Ok(()) => self.status = Some(format!("Copied card {} ({width}×{height}) to the clipboard", self.index)),
+                Err(e) => self.error = Some(format!("Failed to copy card: {e}")),
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            log::warn!("Cannot copy card {} ({}x{}) on Android", self.index, img.width(), img.height());
+            self.error = Some("Copying images is not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let name = format!("card_{}.png", self.index);
+            match encode_png(&img) {
+                Ok(bytes) => {
+                    crate::file_picker::download_bytes(&bytes, &name, "image/png");
+                    self.status = Some(format!("Browsers cannot copy images here; downloaded {name} instead"));
+                }
+                Err(e) => self.error = Some(format!("Failed to copy card: {e}")),
+            }
+        }
+    }
+
+    /// Pick a folder and start writing every card into it as `card_0000.png`, `card_0001.png`, ...
+    fn export_all_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if let Some(dir) = self.file_dialog().pick_folder().inspect(|p| self.remember_dialog_dir(p)) {
+                self.error = None;
+                self.batch_export = Some(BatchExport { dir, next: 0, written: 0, regions: None, group_by_tag: false, skip_empty: self.skip_empty_cards });
+            }
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.error = Some("Exporting all cards to a folder is not supported on the web build".to_owned());
+        }
+    }
+
+    /// Advance a running batch export by a few cards. Indices without a card are skipped; the first
+    /// write failure (e.g. a read-only folder) aborts the run and is reported in `error`.
+    fn step_batch_export(&mut self, ctx: &egui::Context) {
+        let Some(mut job) = self.batch_export.take() else { return };
+        let max = self.max_index();
+        let total = max + 1;
+
+        for _ in 0..EXPORT_CARDS_PER_FRAME {
+            if job.next > max { break; }
+            let index = job.next;
+            job.next += 1;
+            if job.skip_empty && self.is_card_empty(index) { continue; }
+            let Some(img) = self.make_card_rgba(index) else { continue };
+            let files = match &job.regions {
+                None => vec![(job.dir.join(format!("card_{index:04}.png")), img)],
+                Some(regions) => {
+                    let mut files = Vec::new();
+                    for (r, name) in regions {
+                        let Some(crop) = Self::crop_region(&img, r) else { continue };
+                        match region_export_dir(&job.dir, r, job.group_by_tag) {
+                            Ok(dir) => files.push((dir.join(format!("{index:04}_{name}.png")), crop)),
+                            Err(e) => {
+                                self.error = Some(format!("Export failed creating the folder for '{}': {e}", r.name));
+                                self.status = None;
+                                return;
+                            }
+                        }
+                    }
+                    files
+                }
+            };
+            for (path, img) in files {
+                let img = self.orient(img);
+                if let Err(e) = img.save_with_format(&path, image::ImageFormat::Png) {
+                    self.error = Some(format!("Export failed at '{}': {e}", path.display()));
+                    self.status = None;
+                    return;
+                }
+                job.written += 1;
+            }
+        }
+
+        if job.next > max {
+            let what = if job.regions.is_some() { "region images" } else { "cards" };
+            self.status = Some(format!("Exported {} {what} to {}", job.written, job.dir.display()));
+        } else {
+            self.status = Some(format!("Exported {}/{}", job.next, total));
+            self.batch_export = Some(job);
+            ctx.request_repaint();
+        }
+    }
+
+    /// Snapshot `regions` before an edit so it can be undone. Any new edit clears the redo stack.
+    fn push_undo(&mut self) {
+        self.push_undo_snapshot(self.regions.clone());
+    }
+
+    fn push_undo_snapshot(&mut self, snapshot: Vec<Region>) {
+        self.history.undo_stack.push(snapshot);
+        if self.history.undo_stack.len() > UNDO_DEPTH {
+            self.history.undo_stack.remove(0);
+        }
+        self.history.redo_stack.clear();
+    }
+
+    /// Remember the regions as they were when a field edit starts, with region `i` as it was
+    /// (`before`) ahead of the change just made. Later changes of the same edit keep that snapshot.
+    fn begin_fields_edit(&mut self, i: usize, before: Region) {
+        if self.history.region_fields_snapshot.is_some() { return; }
+        let mut snapshot = self.regions.clone();
+        if let Some(r) = snapshot.get_mut(i) { *r = before; }
+        self.history.region_fields_snapshot = Some(snapshot);
+    }
+
+    fn undo(&mut self) {
+        if let Some(prev) = self.history.undo_stack.pop() {
+            self.history.redo_stack.push(std::mem::replace(&mut self.regions, prev));
+            self.clear_selection();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(next) = self.history.redo_stack.pop() {
+            self.history.undo_stack.push(std::mem::replace(&mut self.regions, next));
+            self.clear_selection();
+        }
+    }
+
+    /// Ctrl+Z undoes, Ctrl+Y / Ctrl+Shift+Z redoes. Ignored while a text field has focus so
+    /// text editing keeps its own undo.
+    fn handle_undo_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() { return; }
+        let undo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        let redo = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+        let redo_alt = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+        // check the Shift variant first: consume_shortcut ignores extra modifiers like Shift
+        let (redo_pressed, undo_pressed) = ctx.input_mut(|i| {
+            let r = i.consume_shortcut(&redo_alt) || i.consume_shortcut(&redo);
+            (r, i.consume_shortcut(&undo))
+        });
+        if redo_pressed { self.redo(); }
+        if undo_pressed { self.undo(); }
+    }
+
+    /// A name for a new region based on `base`: trimmed, `region{n}` if that leaves nothing, and with
+    /// `_2`, `_3`, ... appended while it matches an existing region (so exports don't overwrite each other).
+    fn unique_region_name(&self, base: &str) -> String {
+        let base = base.trim();
+        let base = if base.is_empty() { format!("region{}", self.regions.len() + 1) } else { base.to_owned() };
+        let taken = |name: &str| self.regions.iter().any(|r| r.name == name);
+        if !taken(&base) { return base; }
+        (2..).map(|n| format!("{base}_{n}")).find(|name| !taken(name)).unwrap_or(base)
+    }
+
+    /// Append a region pasted as JSON (as written by "Copy JSON"), shrunk/moved to fit on the card.
+    fn paste_region(&mut self, text: &str) {
+        match serde_json::from_str::<Region>(text.trim()) {
+            Ok(mut r) => {
+                r.width = r.width.clamp(1, self.card_width);
+                r.height = r.height.clamp(1, self.card_height);
+                r.x = r.x.min(self.card_width - r.width);
+                r.y = r.y.min(self.card_height - r.height);
+                r.name = self.unique_region_name(&r.name);
+                self.push_undo();
+                self.regions.push(r);
+                self.select_only(self.regions.len() - 1);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("Clipboard does not hold a region: {e}")),
+        }
+    }
+
+    /// Clipboard text arrives as a paste event: after "Paste region" asked the backend for it, or
+    /// on Ctrl+V while no text field has focus.
+    fn handle_region_paste(&mut self, ctx: &egui::Context) {
+        if !self.paste_region_requested && ctx.wants_keyboard_input() { return; }
+        let pasted = ctx.input(|i| {
+            i.events.iter().find_map(|e| match e {
+                egui::Event::Paste(text) => Some(text.clone()),
+                _ => None,
+            })
+        });
+        if let Some(text) = pasted {
+            self.paste_region_requested = false;
+            self.paste_region(&text);
+        }
+    }
+
+    /// The first visible region containing card pixel (`px`, `py`).
+    fn region_at(&self, px: usize, py: usize) -> Option<usize> {
+        self.regions_at(px, py).first().copied()
+    }
+
+    /// Indices of all visible regions containing card pixel `(px, py)`, in list order.
+    fn regions_at(&self, px: usize, py: usize) -> Vec<usize> {
+        (0..self.regions.len())
+            .filter(|&i| {
+                self.regions
+                    .get(i)
+                    .is_some_and(|r| r.visible && px >= r.x && px < r.x + r.width && py >= r.y && py < r.y + r.height)
+            })
+            .collect()
+    }
+
+    /// Selection for a click at card pixel `(px, py)`. Repeated clicks on overlapping regions cycle
+    /// through them, starting after the selected one, so a region under another stays reachable.
+    /// Ctrl+click (`toggle`) adds/removes the first region there; a plain click on empty card
+    /// space deselects.
+    fn click_region(&mut self, px: usize, py: usize, toggle: bool) {
+        let hits = self.regions_at(px, py);
+        let next = match self.selected_region.and_then(|s| hits.iter().position(|&i| i == s)) {
+            Some(pos) => hits.get((pos + 1) % hits.len()),
+            None => hits.first(),
+        };
+        match (hits.first(), next) {
+            (Some(&i), _) if toggle => self.toggle_selected(i),
+            (_, Some(&i)) => self.select_only(i),
+            (None, _) if toggle => {}
+            // only empty card space deselects; clicks elsewhere keep the selection
+            _ => self.clear_selection(),
+        }
+    }
+
+    /// Right-click menu of the preview for the region under the pointer (`context_region`).
+    fn region_context_menu(&mut self, resp: &egui::Response) {
+        resp.context_menu(|ui| {
+            let Some(i) = self.context_region.filter(|&i| i < self.regions.len()) else {
+                ui.close();
+                return;
+            };
+            let Some(r) = self.regions.get(i).cloned() else { return };
+            ui.label(egui::RichText::new(&r.name).strong());
+            ui.horizontal(|ui| {
+                let edit = ui.add(egui::TextEdit::singleline(&mut self.inputs.context_rename).desired_width(120.0));
+                let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if (ui.button("Rename").clicked() || enter) && self.inputs.context_rename.trim() != r.name {
+                    let name = self.unique_region_name(&self.inputs.context_rename);
+                    self.push_undo();
+                    if let Some(r) = self.regions.get_mut(i) { r.name = name; }
+                    ui.close();
+                }
+            });
+            if ui.button("Duplicate").clicked() {
+                let mut copy = r.clone();
+                copy.name = self.unique_region_name(&r.name);
+                copy.locked = false;
+                // offset so the copy is visible, as far as the card allows
+                copy.x = (r.x + 8).min(self.card_width.saturating_sub(r.width));
+                copy.y = (r.y + 8).min(self.card_height.saturating_sub(r.height));
+                self.push_undo();
+                self.regions.push(copy);
+                self.select_only(self.regions.len() - 1);
+                ui.close();
+            }
+            if ui.add_enabled(!r.locked, egui::Button::new("Delete")).clicked() {
+                self.push_undo();
+                self.regions.remove(i);
+                self.clear_selection();
+                ui.close();
+            }
+            if ui.button(if r.locked { "Unlock" } else { "Lock" }).clicked() {
+                self.push_undo();
+                if let Some(r) = self.regions.get_mut(i) { r.locked = !r.locked; }
+                ui.close();
+            }
+            if ui.button("Copy JSON").clicked() {
+                match serde_json::to_string(&r) {
+                    Ok(json) => ui.ctx().copy_text(json),
+                    Err(e) => self.error = Some(format!("Failed to copy region: {e}")),
+                }
+                ui.close();
+            }
+        });
+    }
+
+    /// Drop selected indices that no longer name a region (e.g. a hand-edited saved state).
+    fn validate_selection(&mut self) {
+        let len = self.regions.len();
+        self.selected_regions.retain(|&i| i < len);
+        if let Some(i) = self.selected_region
+            && (i >= len || !self.selected_regions.contains(&i))
+        {
+            self.selected_region = self.selected_regions.first().copied();
+        }
+    }
+
+    /// Replace the selection, keeping the primary region if it is still selected.
+    fn set_selection(&mut self, selection: std::collections::BTreeSet<usize>) {
+        self.selected_regions = selection;
+        if !self.selected_region.is_some_and(|i| self.selected_regions.contains(&i)) {
+            self.selected_region = self.selected_regions.first().copied();
+        }
+    }
+
+    fn select_all_regions(&mut self) {
+        self.set_selection((0..self.regions.len()).collect());
+    }
+
+    fn invert_selection(&mut self) {
+        let inverted = (0..self.regions.len()).filter(|i| !self.selected_regions.contains(i)).collect();
+        self.set_selection(inverted);
+    }
+
+    /// Select every region that overlaps another one.
+    fn select_overlapping(&mut self) {
+        let overlapping = self.overlapping_pairs().into_iter().flat_map(<[usize; 2]>::from).collect();
+        self.set_selection(overlapping);
+    }
+
+    fn clear_selection(&mut self) {
+        self.selected_region = None;
+        self.selected_regions.clear();
+    }
+
+    /// Make region `i` the only selected one.
+    fn select_only(&mut self, i: usize) {
+        self.selected_region = Some(i);
+        self.selected_regions = std::iter::once(i).collect();
+    }
+
+    /// Add region `i` to the selection (making it primary), or take it out if already selected.
+    fn toggle_selected(&mut self, i: usize) {
+        if self.selected_regions.remove(&i) {
+            if self.selected_region == Some(i) {
+                self.selected_region = self.selected_regions.first().copied();
+            }
+        } else {
+            self.selected_regions.insert(i);
+            self.selected_region = Some(i);
+        }
+    }
+
+    /// Delete all selected regions as one undo step.
+    fn delete_selected_regions(&mut self) {
+        if self.selected_regions.is_empty() { return; }
+        self.push_undo();
+        // back to front so earlier indices stay valid
+        for i in std::mem::take(&mut self.selected_regions).into_iter().rev() {
+            if self.regions.get(i).is_some_and(|r| !r.locked) { self.regions.remove(i); }
+        }
+        self.clear_selection();
+    }
+
+    /// Clip every unlocked region that reaches past the card to the card's bounds.
+    fn clamp_regions_to_card(&mut self) {
+        let size = [self.card_width, self.card_height];
+        let (locked, offending): (Vec<usize>, Vec<usize>) = (0..self.regions.len())
+            .filter(|&i| self.regions.get(i).is_some_and(|r| r.out_of_bounds(size)))
+            .partition(|&i| self.regions.get(i).is_some_and(|r| r.locked));
+        if offending.is_empty() { return; }
+        self.push_undo();
+        for i in &offending {
+            if let Some(r) = self.regions.get_mut(*i) { r.clamp_to(size); }
+        }
+        self.status = Some(if locked.is_empty() {
+            format!("Clamped {} regions to the card", offending.len())
+        } else {
+            format!("Clamped {} regions to the card; {} locked ones left as they are", offending.len(), locked.len())
+        });
+    }
+
+    /// Scale every region by the ratio between the `new` and `old` card sizes (width, height), so
+    /// regions drawn for one card size keep covering the same part of the card at another.
+    fn rescale_regions(&mut self, old: (usize, usize), new: (usize, usize)) {
+        if old == new || old.0 == 0 || old.1 == 0 { return; }
+        // rounded v * to / from
+        let scale = |v: usize, from: usize, to: usize| (v * to + from / 2) / from;
+        for r in &mut self.regions {
+            let right = scale(r.x + r.width, old.0, new.0);
+            let bottom = scale(r.y + r.height, old.1, new.1);
+            r.x = scale(r.x, old.0, new.0);
+            r.y = scale(r.y, old.1, new.1);
+            r.width = right.saturating_sub(r.x).max(1);
+            r.height = bottom.saturating_sub(r.y).max(1);
+        }
+    }
+
+    /// Index pairs `(i, j)`, `i < j`, of regions whose rectangles intersect.
+    fn overlapping_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for (i, a) in self.regions.iter().enumerate() {
+            for (j, b) in self.regions.iter().enumerate().skip(i + 1) {
+                if a.overlaps(b) { pairs.push((i, j)); }
+            }
+        }
+        pairs
+    }
+
+    /// One-line summary of the atlas, card and regions, with the preview's pointer state
+    /// in a collapsible debug section.
+    fn status_bar_ui(&self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            let file = self.atlas.as_ref().and(self.atlas_path.as_deref()).map_or("(no atlas)", |p| {
+                Path::new(p).file_name().and_then(|f| f.to_str()).unwrap_or(p)
+            });
+            ui.label(file).on_hover_text(self.atlas_path.as_deref().unwrap_or_default());
+            ui.separator();
+            ui.label(format!("{}×{} px", self.atlas_size[0], self.atlas_size[1]));
+            if self.atlas.is_some() {
+                // decoded RGBA in memory, which is what matters for large sheets (not the file size)
+                let bytes = self.atlas_size[0] * self.atlas_size[1] * 4;
+                let mut weight = format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0));
+                if let Some(took) = self.atlas_jobs.atlas_load_time {
+                    weight += &format!(", loaded in {:.2} s", took.as_secs_f64());
+                }
+                ui.label(weight).on_hover_text("Memory taken by the decoded atlas (RGBA, 4 bytes per pixel)");
+            }
+            ui.separator();
+            ui.label(format!("{} cols × {} rows ({} cards)", self.cols(), self.rows(), self.cols() * self.rows()));
+            let [lx, ly] = self.leftover_pixels();
+            // a trailing margin as wide as the leading one is expected
+            let odd = |left: usize, margin: usize| left != 0 && left != margin;
+            if self.atlas.is_some() && (odd(lx, self.margin_x) || odd(ly, self.margin_y)) {
+                ui.colored_label(egui::Color32::YELLOW, format!("⚠ leftover: {lx}px right, {ly}px bottom"))
+                    .on_hover_text("The atlas is not an exact multiple of the card size; the card width/height may be wrong");
+            }
+            ui.separator();
+            ui.label(format!("card {} / {}", self.index, self.max_index()));
+            if self.atlas.is_some() {
+                let [sx, sy, sw, sh] = self.card_cell();
+                ui.label(format!("src: ({sx}, {sy}) size {sw}×{sh}"))
+                    .on_hover_text("Atlas pixels the current card is cut from (margin, spacing and offset included)");
+            }
+            if let Some((_, empty)) = self.previews.empty_cards.filter(|(_, n)| *n > 0) {
+                ui.label(format!("({empty} empty)")).on_hover_text("Fully transparent cards; tick \"Skip empty\" to pass over them");
+            }
+            ui.separator();
+            ui.label(format!("{} regions", self.regions.len()));
+            ui.separator();
+            ui.label(format!("zoom {:.0}%", self.zoom * 100.0));
+        });
+        if self.show_regions_panel && !self.debug_readout.is_empty() {
+            ui.collapsing("Debug", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (i, line) in self.debug_readout.iter().enumerate() {
+                        if i > 0 { ui.separator(); }
+                        ui.label(line);
+                    }
+                });
+            });
+        }
+    }
+
+    /// Horizontal strip of thumbnails for the cards around `index`; clicking one selects it.
+    /// Thumbnails are cut lazily and dropped again once they leave the window.
+    fn filmstrip_ui(&mut self, ui: &mut egui::Ui) {
+        let layout = [self.card_width, self.card_height, self.margin_x, self.margin_y, self.spacing_x, self.spacing_y];
+        if layout != self.previews.thumbnails_layout {
+            self.previews.thumbnails.clear();
+            self.previews.thumbnails_layout = layout;
+        }
+        if self.atlas.is_none() { return; }
+
+        let lo = self.index.saturating_sub(FILMSTRIP_RADIUS);
+        let hi = (self.index + FILMSTRIP_RADIUS).min(self.max_index());
+        self.previews.thumbnails.retain(|i, _| (lo..=hi).contains(i));
+
+        let mut budget = THUMBNAILS_PER_FRAME;
+        for i in lo..=hi {
+            if budget == 0 { break; }
+            if self.previews.thumbnails.contains_key(&i) { continue; }
+            budget -= 1;
+            let Some(card) = self.make_oriented_card(i) else { continue };
+            let h = (u64::from(card.height()) * u64::from(THUMBNAIL_WIDTH) / u64::from(card.width().max(1))).max(1) as u32;
+            let thumb = image::imageops::thumbnail(&card, THUMBNAIL_WIDTH, h);
+            let img = ColorImage::from_rgba_unmultiplied([thumb.width() as usize, thumb.height() as usize], thumb.as_raw());
+            self.previews.thumbnails.insert(i, ui.ctx().load_texture(format!("thumbnail_{i}"), img, TextureOptions::LINEAR));
+        }
+        if budget == 0 { ui.ctx().request_repaint(); }
+
+        let scroll_to_current = self.filmstrip_focus != Some(self.index);
+        self.filmstrip_focus = Some(self.index);
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for i in lo..=hi {
+                    let Some(tex) = self.previews.thumbnails.get(&i) else {
+                        ui.add_sized([THUMBNAIL_WIDTH as f32, 40.0], egui::Spinner::new());
+                        continue;
+                    };
+                    let current = i == self.index;
+                    let note = self.card_notes.get(&i);
+                    let resp = ui
+                        .add(egui::Button::image(egui::Image::new((tex.id(), tex.size_vec2()))).selected(current))
+                        .on_hover_text(note.map_or_else(|| format!("Card {i}"), |n| format!("Card {i}\n{n}")));
+                    // cards with a note get a dot in the corner
+                    if note.is_some() {
+                        ui.painter().circle_filled(resp.rect.right_top() + egui::vec2(-7.0, 7.0), 4.0, egui::Color32::YELLOW);
+                    }
+                    if resp.clicked() { self.index = i; }
+                    if current && scroll_to_current { resp.scroll_to_me(Some(egui::Align::Center)); }
+                }
+            });
+        });
+    }
+
+    /// Replace the note of card `index`; a blank note removes it.
+    fn set_card_note(&mut self, index: usize, note: String) {
+        if note.trim().is_empty() {
+            self.card_notes.remove(&index);
+        } else {
+            self.card_notes.insert(index, note);
+        }
+    }
+
+    /// Side panel with the current card's note and links to the other cards that have one.
+    fn notes_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading(format!("Notes — card {}", self.index));
+        let mut note = self.card_notes.get(&self.index).cloned().unwrap_or_default();
+        let edit = egui::TextEdit::multiline(&mut note).hint_text("Notes on this card").desired_rows(8).desired_width(f32::INFINITY);
+        if ui.add(edit).changed() {
+            self.set_card_note(self.index, note);
+        }
+        ui.separator();
+        let mut noted: Vec<usize> = self.card_notes.keys().copied().collect();
+        noted.sort_unstable();
+        ui.label(format!("{} cards with notes", noted.len()));
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for i in noted {
+                let first_line = self.card_notes.get(&i).and_then(|n| n.lines().next()).unwrap_or_default();
+                if ui.selectable_label(i == self.index, format!("{i}: {first_line}")).clicked() {
+                    self.index = i;
+                }
+            }
+        });
+    }
+
+    /// Side panel listing the regions, with the pending region, the selected region's fields and
+    /// the load/save/export actions.
+    fn regions_panel_ui(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Regions");
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            ui.add(egui::TextEdit::singleline(&mut self.inputs.region_filter).hint_text("Filter by name or tag"));
+            if !self.inputs.region_filter.is_empty() && ui.small_button("✖").on_hover_text("Clear the filter").clicked() {
+                self.inputs.region_filter.clear();
+            }
+        });
+        ui.separator();
+
+        if self.card_transformed() {
+            ui.colored_label(egui::Color32::YELLOW, "Rotated/flipped view: reset the orientation to draw or edit regions on the card.");
+        }
+
+        self.pending_region_ui(ui);
+
+        let out_of_bounds = self.regions.iter().filter(|r| r.out_of_bounds([self.card_width, self.card_height])).count();
+        ui.horizontal(|ui| {
+            ui.label("Saved regions:");
+            ui.label("sort by");
+            ui.selectable_value(&mut self.region_sort, RegionSort::Created, "created");
+            ui.selectable_value(&mut self.region_sort, RegionSort::Name, "name");
+            ui.selectable_value(&mut self.region_sort, RegionSort::Area, "area");
+        });
+        if out_of_bounds > 0 {
+            ui.horizontal(|ui| {
+                ui.colored_label(egui::Color32::RED, format!("{out_of_bounds} regions reach past the card"));
+                if ui.button("Clamp all to bounds").on_hover_text("Clip them to the card (locked regions are left as they are)").clicked() {
+                    self.clamp_regions_to_card();
+                }
+            });
+        }
+        // Field edits below (visibility, color, numbers) are undone as one step per drag or typing
+        // session: the state before the first change is kept until no field is active any more.
+        let mut active = self.region_list_ui(ui);
+
+        let overlaps = self.overlapping_pairs();
+        if let Some(&(a, b)) = overlaps.first() {
+            let mut involved: Vec<usize> = overlaps.iter().flat_map(|&pair| <[usize; 2]>::from(pair)).collect();
+            involved.sort_unstable();
+            involved.dedup();
+            let warning = egui::RichText::new(format!("⚠ {} overlapping regions", involved.len())).color(egui::Color32::ORANGE);
+            if ui.link(warning).on_hover_text("Select the first overlapping pair").clicked() {
+                self.select_only(a);
+                self.selected_regions.insert(b);
+                self.highlighted_overlap = Some((a, b));
+            }
+        }
+
+        if ui.button("Paste region").on_hover_text("Add a region copied as JSON (or press Ctrl+V)").clicked() {
+            self.paste_region_requested = true;
+            ui.ctx().send_viewport_cmd(egui::ViewportCommand::RequestPaste);
+        }
+
+        active |= self.region_fields_ui(ui);
+        if !active
+            && let Some(snapshot) = self.history.region_fields_snapshot.take()
+            && snapshot != self.regions
+        {
+            self.push_undo_snapshot(snapshot);
+        }
+
+        // Live crop of the selected region from the current card
+        if let Some((_, tex)) = &self.previews.region_preview {
+            ui.separator();
+            ui.label("Selected region preview:");
+            let size = tex.size_vec2();
+            let scale = (ui.available_width() / size.x).min(200.0 / size.y).min(4.0);
+            ui.image((tex.id(), size * scale));
+        }
+
+        ui.add_space(8.0);
+        self.region_actions_ui(ui);
+    }
+
+    /// The region drawn on the card but not added yet, with its name.
+    fn pending_region_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some([px, py, pw, ph]) = self.pending_region {
+            ui.label("New region pending:");
+            ui.horizontal(|ui| {
+                ui.label(format!("{pw}×{ph} @ {px},{py}"));
+                if ui.button("Add").on_hover_text("Enter").clicked() {
+                    self.add_pending_region();
+                }
+                if ui.button("Cancel").on_hover_text("Escape").clicked() {
+                    self.cancel_pending_region();
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Name:");
+                let resp = ui.add(egui::TextEdit::singleline(&mut self.new_region_name));
+                // the field gives up focus on Enter and Escape, which then confirm or cancel
+                if resp.lost_focus() {
+                    let (enter, escape) = ui.input(|i| (i.key_pressed(egui::Key::Enter), i.key_pressed(egui::Key::Escape)));
+                    if enter { self.add_pending_region(); }
+                    if escape { self.cancel_pending_region(); }
+                }
+            });
+        } else {
+            ui.label("No pending region.");
+        }
+        ui.separator();
+    }
+
+    /// The saved regions, sorted and filtered, one row each. Returns whether one of their fields is
+    /// being edited.
+    fn region_list_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut active = false;
+        let mut to_delete: Option<usize> = None;
+        let mut copy_error: Option<String> = None;
+        let card_size = [self.card_width, self.card_height];
+        let mut order: Vec<usize> = (0..self.regions.len()).collect();
+        match self.region_sort {
+            RegionSort::Created => {}
+            RegionSort::Name => order.sort_by_cached_key(|i| self.regions.get(*i).map(|r| r.name.to_lowercase())),
+            // largest first, so outliers are at the ends
+            RegionSort::Area => order.sort_by_key(|i| std::cmp::Reverse(self.regions.get(*i).map(Region::area))),
+        }
+        // Rows keep their index into `regions`, so selection and deletion are unaffected by the filter
+        let filter = self.inputs.region_filter.trim().to_lowercase();
+        if !filter.is_empty() {
+            order.retain(|i| self.regions.get(*i).is_some_and(|r| r.matches_filter(&filter)));
+            ui.label(format!("{} of {} regions match", order.len(), self.regions.len()));
+        }
+        let mut clicked_row: Option<(usize, bool)> = None; // (index, toggle membership)
+        let mut center_row: Option<usize> = None; // double-clicked: select it and center the view on it
+        let mut recolor: Option<[u8; 4]> = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for i in order {
+                let Some(r) = self.regions.get_mut(i) else { continue };
+                let before = (r.visible, r.locked, r.color);
+                ui.horizontal(|ui| {
+                    ui.toggle_value(&mut r.visible, "👁").on_hover_text("Show this region over the preview");
+                    ui.toggle_value(&mut r.locked, "🔒").on_hover_text("Lock against moving, resizing and deleting");
+                    let [cr, cg, cb, ca] = r.color.unwrap_or(DEFAULT_REGION_COLOR);
+                    let mut color = egui::Color32::from_rgba_unmultiplied(cr, cg, cb, ca);
+                    let resp = egui::color_picker::color_edit_button_srgba(ui, &mut color, egui::color_picker::Alpha::OnlyBlend);
+                    if resp.changed() {
+                        r.color = Some(color.to_srgba_unmultiplied());
+                        // recoloring a selected region recolors the whole selection
+                        if self.selected_regions.contains(&i) { recolor = r.color; }
+                        active = true;
+                    }
+                    let selected = self.selected_regions.contains(&i);
+                    let resp = ui
+                        .selectable_label(selected, &r.name)
+                        .on_hover_text("Shift/Ctrl+click to add to the selection, double-click to center the view on it");
+                    if resp.clicked() {
+                        let toggle = ui.input(|inp| inp.modifiers.shift || inp.modifiers.command);
+                        clicked_row = Some((i, toggle));
+                    }
+                    if resp.double_clicked() { center_row = Some(i); }
+                    if !r.tags.is_empty() {
+                        ui.weak(r.tags.iter().map(|t| format!("#{t}")).collect::<Vec<_>>().join(" "));
+                    }
+                    let geometry = format!("{}x{} @ {},{}", r.width, r.height, r.x, r.y);
+                    if r.out_of_bounds(card_size) {
+                        egui::Frame::new().stroke(egui::Stroke::new(1.0, egui::Color32::RED)).inner_margin(2.0).show(ui, |ui| {
+                            ui.colored_label(egui::Color32::RED, geometry);
+                        })
+                        .response
+                        .on_hover_text(format!("Reaches past the {}x{} card", card_size[0], card_size[1]));
+                    } else {
+                        ui.label(geometry);
+                    }
+                    ui.weak(format!("{} px² · {}", r.area(), r.aspect_label()));
+                    if ui.small_button("Copy JSON").on_hover_text("Copy this region to the clipboard").clicked() {
+                        match serde_json::to_string(&*r) {
+                            Ok(json) => ui.ctx().copy_text(json),
+                            Err(e) => copy_error = Some(e.to_string()),
+                        }
+                    }
+                    if ui.add_enabled(!r.locked, egui::Button::new("Delete").small()).clicked() {
+                        to_delete = Some(i);
+                    }
+                });
+                if (r.visible, r.locked, r.color) != before {
+                    let mut prev = r.clone();
+                    (prev.visible, prev.locked, prev.color) = before;
+                    self.begin_fields_edit(i, prev);
+                }
+            }
+        });
+
+        if let Some(e) = copy_error {
+            self.error = Some(format!("Failed to copy region: {e}"));
+        }
+        if let Some(color) = recolor {
+            for i in &self.selected_regions {
+                if let Some(r) = self.regions.get_mut(*i) { r.color = Some(color); }
+            }
+        }
+        match clicked_row {
+            Some((i, true)) => self.toggle_selected(i),
+            Some((i, false)) => self.select_only(i),
+            None => {}
+        }
+        if let Some(i) = center_row {
+            self.select_only(i);
+            self.request_center_on_region();
+        }
+
+        if let Some(i) = to_delete {
+            if self.regions.get(i).is_some_and(|r| !r.locked) {
+                self.push_undo();
+                self.regions.remove(i);
+                // indices after the removed region shift down
+                self.clear_selection();
+            }
+        }
+        active
+    }
+
+    /// Numeric editing of the selected region. Returns whether one of its fields is being edited.
+    fn region_fields_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut active = false;
+        let (card_w, card_h) = (self.card_width, self.card_height);
+        let mut edited: Option<(usize, Region)> = None;
+        if let Some((i, r)) = self.selected_region.and_then(|i| Some((i, self.regions.get_mut(i)?))) {
+            let before = r.clone();
+            ui.separator();
+            ui.label(format!("Selected region: {} px², aspect {}", r.area(), r.aspect_label()));
+            let x_out = r.x + r.width > card_w;
+            let y_out = r.y + r.height > card_h;
+            let label = |ui: &mut egui::Ui, text: &str, out: bool| {
+                if out { ui.colored_label(egui::Color32::RED, text) } else { ui.label(text) }
+            };
+            egui::Grid::new("region_fields").num_columns(2).show(ui, |ui| {
+                ui.label("Name:");
+                let resp = ui.text_edit_singleline(&mut r.name);
+                active |= resp.has_focus();
+                ui.end_row();
+                // Edited as comma-separated text; the raw text is kept while typing so a
+                // trailing comma is not swallowed by re-joining the parsed tags
+                ui.label("Tags:");
+                let mut text = match &self.inputs.tags_input {
+                    Some((j, text)) if *j == i => text.clone(),
+                    _ => r.tags.join(", "),
+                };
+                let resp = ui.add(egui::TextEdit::singleline(&mut text).hint_text("e.g. text, icon"));
+                if resp.changed() { r.tags = parse_tags(&text); }
+                active |= resp.has_focus();
+                self.inputs.tags_input = resp.has_focus().then_some((i, text));
+                ui.end_row();
+                // a region is at least one pixel wide and high; a locked one is not moved or resized
+                let locked = r.locked;
+                for (text, value, min, max, out) in [
+                    ("X:", &mut r.x, 0, card_w, x_out),
+                    ("Y:", &mut r.y, 0, card_h, y_out),
+                    ("Width:", &mut r.width, 1, card_w.max(1), x_out),
+                    ("Height:", &mut r.height, 1, card_h.max(1), y_out),
+                ] {
+                    label(ui, text, out);
+                    let resp = ui.add_enabled(!locked, egui::DragValue::new(value).range(min..=max));
+                    active |= resp.has_focus() || resp.dragged();
+                    ui.end_row();
+                }
+            });
+            if x_out || y_out {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("Region extends past the {card_w}×{card_h} card"),
+                );
+            }
+            if *r != before { edited = Some((i, before)); }
+        }
+        if let Some((i, before)) = edited {
+            self.begin_fields_edit(i, before);
+        }
+        active
+    }
+
+    /// Undo, selection, and loading, saving and exporting the regions.
+    fn region_actions_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            if ui.add_enabled(!self.history.undo_stack.is_empty(), egui::Button::new("Undo")).on_hover_text("Ctrl+Z").clicked() {
+                self.undo();
+            }
+            if ui.add_enabled(!self.history.redo_stack.is_empty(), egui::Button::new("Redo")).on_hover_text("Ctrl+Y").clicked() {
+                self.redo();
+            }
+            ui.menu_button("Select", |ui| {
+                if ui.button("All").clicked() {
+                    self.select_all_regions();
+                }
+                if ui.button("Invert").clicked() {
+                    self.invert_selection();
+                }
+                if ui.button("Overlapping").on_hover_text("Select every region that overlaps another").clicked() {
+                    self.select_overlapping();
+                }
+            });
+            let n = self.selected_regions.len();
+            if ui.add_enabled(n > 0, egui::Button::new(format!("Delete selected ({n})"))).on_hover_text("Delete").clicked() {
+                self.delete_selected_regions();
+            }
+            if ui.add_enabled(!self.regions.is_empty(), egui::Button::new("Clear All")).clicked() {
+                self.prompts.confirm_clear = true;
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Save...").clicked() {
+                self.save_regions_dialog();
+            }
+            let mut atlas_space = self.save_coordinate_space == CoordinateSpace::Atlas;
+            if ui.checkbox(&mut atlas_space, "in atlas pixels")
+                .on_hover_text("Save coordinates offset to the current card's place in the atlas instead of card-relative")
+                .changed()
+            {
+                self.save_coordinate_space = if atlas_space { CoordinateSpace::Atlas } else { CoordinateSpace::Card };
+            }
+            ui.menu_button("Import", |ui| {
+                if ui.button("Load...").clicked() {
+                    self.load_regions_dialog();
+                }
+                if ui.button("Load CSV...").on_hover_text("Replace the regions with name,x,y,width,height rows").clicked() {
+                    self.load_regions_csv_dialog();
+                }
+                if ui.button("Import atlas JSON...")
+                    .on_hover_text("Add the frames of a TexturePacker JSON (Hash) file that lie in the current card")
+                    .clicked()
+                {
+                    self.import_texturepacker_dialog();
+                }
+            });
+            ui.menu_button("Export", |ui| {
+                if ui.button("Export .rs...").on_hover_text("Save the regions as a Rust `const REGIONS` table").clicked() {
+                    self.export_regions_rust_dialog();
+                }
+                ui.separator();
+                if ui.button("Export SVG...").on_hover_text("Save the regions as labeled rectangles in an SVG the size of the card").clicked() {
+                    self.export_svg_dialog();
+                }
+                ui.checkbox(&mut self.svg_embed_card, "with card")
+                    .on_hover_text("Embed the current card as the SVG's background");
+                ui.separator();
+                if ui.button("Export atlas manifest...")
+                    .on_hover_text("Save one JSON file with the atlas, card grid and every card's regions")
+                    .clicked()
+                {
+                    self.export_manifest_dialog();
+                }
+                ui.checkbox(&mut self.manifest_shared_regions, "shared regions")
+                    .on_hover_text("List the regions once in the manifest instead of repeating them for every card");
+                ui.separator();
+                if ui.button("Export regions...").on_hover_text("Save each region of the current card as a PNG").clicked() {
+                    self.export_regions_dialog();
+                }
+                if ui
+                    .add_enabled(self.batch_export.is_none(), egui::Button::new("Export for all cards..."))
+                    .on_hover_text("Save each region of every card as {index}_{name}.png")
+                    .clicked()
+                {
+                    self.export_all_regions_dialog();
+                }
+                ui.checkbox(&mut self.export_group_by_tag, "group by tag")
+                    .on_hover_text("Put each region's images in a subfolder named after its first tag");
+            });
+        });
+    }
+
+    /// Open an atlas image dropped onto the window: from its path on desktop, or from the in-memory
+    /// bytes the browser hands over on web. While files are dragged over the window it is dimmed
+    /// with a hint.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let (hovering, dropped) = ctx.input(|i| (!i.raw.hovered_files.is_empty(), i.raw.dropped_files.clone()));
+
+        if hovering {
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_target")));
+            let screen = ctx.content_rect();
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.rect_stroke(screen.shrink(4.0), 8.0, egui::Stroke::new(3.0, egui::Color32::LIGHT_BLUE), egui::StrokeKind::Inside);
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                format!("Drop an atlas image to open it ({})", supported_extensions().join(", ")),
+                egui::FontId::proportional(20.0),
+                egui::Color32::WHITE,
+            );
+        }
+
+        let [file] = dropped.as_slice() else {
+            if !dropped.is_empty() {
+                self.error = Some(format!("Drop a single atlas image ({} files were dropped)", dropped.len()));
+            }
+            return;
+        };
+        let result = match (&file.path, &file.bytes) {
+            (Some(path), _) => self.load_atlas(path),
+            (None, Some(bytes)) => self.load_atlas_bytes(bytes, &file.name),
+            (None, None) => Err(format!("Dropped file '{}' could not be read", file.name)),
+        };
+        match result {
+            Ok(()) => self.error = None,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Built-in card formats followed by the user's own; `selected_preset` indexes this list.
+    fn card_formats(&self) -> Vec<(String, usize, usize)> {
+        CARD_FORMATS
+            .iter()
+            .map(|(n, w, h)| ((*n).to_owned(), *w, *h))
+            .chain(self.user_presets.iter().cloned())
+            .collect()
+    }
+
+    /// Drop unusable user presets from persisted state (no name, a built-in or repeated name) and
+    /// clamp their sizes; the selected preset follows its entry or falls back to "Custom".
+    fn validate_presets(&mut self, source: &str) {
+        let selected = self.selected_preset.and_then(|i| self.card_formats().get(i).map(|(n, _, _)| n.clone()));
+        let before = self.user_presets.clone();
+        let mut seen: Vec<String> = CARD_FORMATS.iter().map(|(n, _, _)| (*n).to_owned()).collect();
+        self.user_presets.retain_mut(|(name, w, h)| {
+            *name = name.trim().to_owned();
+            if name.is_empty() || seen.contains(name) { return false; }
+            seen.push(name.clone());
+            [*w, *h] = clamp_card_size(*w, *h).0;
+            true
+        });
+        if self.user_presets != before {
+            self.warning = Some(format!("Some card format presets from {source} were invalid and have been fixed or removed"));
+        }
+        self.selected_preset = selected.and_then(|name| self.card_formats().iter().position(|(n, _, _)| *n == name));
+    }
+
+    /// Save the current card size under `name`, replacing a user preset of the same name.
+    /// Built-in names are taken.
+    fn save_user_preset(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() { return; }
+        if CARD_FORMATS.iter().any(|(n, _, _)| *n == name) {
+            self.warning = Some(format!("'{name}' is a built-in format; pick another name"));
+            return;
+        }
+        let preset = (name.to_owned(), self.card_width, self.card_height);
+        match self.user_presets.iter().position(|(n, _, _)| n == name) {
+            Some(i) => { if let Some(p) = self.user_presets.get_mut(i) { *p = preset; } }
+            None => self.user_presets.push(preset),
+        }
+        self.selected_preset = self.card_formats().iter().position(|(n, _, _)| n == name);
+        self.prompts.new_preset_name = None;
+    }
+
+    /// Guess the card size from the atlas by looking for regularly spaced card boundaries/gutters.
+    fn detect_grid(&self) -> Option<(usize, usize)> {
+        crate::grid_detect::detect_card_size(self.atlas.as_ref()?)
+    }
+
+    /// Whole atlas scaled to fit, with a line at every card boundary. The current card is
+    /// highlighted; clicking a cell selects that card.
+    fn atlas_grid_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(atlas) = self.atlas.as_deref() else { return };
+        let tex = self.previews.atlas_texture.get_or_insert_with(|| {
+            // The GPU rejects textures above its limit; the overview then gets a downscaled copy
+            // (card previews are cut from the full-resolution atlas and are unaffected)
+            let max_side = ui.ctx().input(|i| i.max_texture_side) as u32;
+            let (w, h) = atlas.dimensions();
+            let img = if w.max(h) > max_side {
+                let factor = f64::from(max_side) / f64::from(w.max(h));
+                let (sw, sh) = ((f64::from(w) * factor) as u32, (f64::from(h) * factor) as u32);
+                let small = image::imageops::resize(atlas, sw.max(1), sh.max(1), image::imageops::FilterType::Triangle);
+                self.status = Some(format!(
+                    "Atlas {w}×{h} exceeds the GPU texture limit of {max_side}px; the grid overview shows it downscaled to {}×{}",
+                    small.width(),
+                    small.height()
+                ));
+                ColorImage::from_rgba_unmultiplied([small.width() as usize, small.height() as usize], small.as_raw())
+            } else {
+                ColorImage::from_rgba_unmultiplied([w as usize, h as usize], atlas.as_raw())
+            };
+            ui.ctx().load_texture("atlas_overview", img, TextureOptions::LINEAR)
+        });
+
+        let avail = ui.available_size();
+        let [aw, ah] = [self.atlas_size[0] as f32, self.atlas_size[1] as f32];
+        let scale = ((avail.x - 20.0).max(10.0) / aw).min((avail.y - 20.0).max(10.0) / ah);
+        let resp = ui.add(egui::Image::new((tex.id(), egui::vec2(aw, ah) * scale)).sense(egui::Sense::click()));
+        let rect = resp.rect;
+        let painter = ui.painter_at(rect);
+
+        let (cols, rows) = (self.cols(), self.rows());
+        // Screen rect of a card cell; with margins and spacing the cells no longer share edges
+        let cell_rect = |col: usize, row: usize| {
+            let [x, y] = self.card_origin(col, row);
+            egui::Rect::from_min_size(
+                rect.min + egui::vec2(x as f32, y as f32) * scale,
+                egui::vec2(self.card_width as f32, self.card_height as f32) * scale,
+            )
+        };
+        let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(255, 255, 0, 160));
+        for row in 0..rows {
+            for col in 0..cols {
+                painter.rect_stroke(cell_rect(col, row), 0.0, stroke, egui::StrokeKind::Inside);
+            }
+        }
+        if cols > 0 {
+            let cell = cell_rect(self.index % cols, self.index / cols);
+            painter.rect_stroke(cell, 0.0, egui::Stroke::new(2.0, egui::Color32::LIGHT_BLUE), egui::StrokeKind::Inside);
+        }
 
-        // On wasm, read ownership confirmation from localStorage if present
-        #[cfg(target_arch = "wasm32")]
+        if resp.clicked()
+            && let Some(pos) = resp.interact_pointer_pos()
         {
-            if let Some(window) = web_sys::window() {
-                if let Ok(Some(ls)) = window.local_storage() {
-                    if let Ok(Some(val)) = ls.get_item("wotr_helper:ownership_confirmed") {
-                        if val == "1" {
-                            this.ownership_confirmed = true;
-                        }
-                    }
+            // Clicks in a gap or the margin select nothing; either way the clicked atlas pixel is
+            // reported, e.g. to find the card an atlas-absolute region came from
+            let p = (pos - rect.min) / scale;
+            let px = [p.x.max(0.0) as usize, p.y.max(0.0) as usize];
+            match self.grid_layout().card_at(px) {
+                Some(i) => {
+                    self.index = i;
+                    self.status = Some(format!("Atlas pixel {},{} is in card {i} (row {}, col {})", px[0], px[1], i / cols, i % cols));
                 }
+                None => self.status = Some(format!("Atlas pixel {},{} is not on any card (margin or gap)", px[0], px[1])),
             }
         }
-
-        this
     }
 
-    fn load_atlas(&mut self, path: &Path) -> Result<(), String> {
-        let img = image::open(path).map_err(|e| e.to_string())?.to_rgba8();
-        let (w, h) = img.dimensions();
-        self.atlas = Some(img);
-        self.atlas_size = [w as usize, h as usize];
-        self.atlas_path = Some(path.to_string_lossy().to_string());
-        // Invalidate any existing texture preview; caller should call ensure_texture after
-        self.texture = None;
-        self.last_index = None;
-        Ok(())
+    /// Crop a region out of a card image, clamping the rectangle to the card bounds.
+    /// Returns `None` when nothing of the region lies on the card.
+    fn crop_region(card: &image::RgbaImage, r: &Region) -> Option<image::RgbaImage> {
+        let (cw, ch) = (card.width() as usize, card.height() as usize);
+        let x0 = r.x.min(cw);
+        let y0 = r.y.min(ch);
+        let x1 = r.x.saturating_add(r.width).min(cw);
+        let y1 = r.y.saturating_add(r.height).min(ch);
+        if x1 <= x0 || y1 <= y0 { return None; }
+        Some(image::imageops::crop_imm(card, x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32).to_image())
     }
 
-    /// Load atlas image from raw bytes (used by the web file picker)
-    fn load_atlas_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
-        let img = image::load_from_memory(bytes).map_err(|e| e.to_string())?.to_rgba8();
-        let (w, h) = img.dimensions();
-        self.atlas = Some(img);
-        self.atlas_size = [w as usize, h as usize];
-        // no real path when loading from a blob; set a friendly label
-        self.atlas_path = Some("(selected)".to_owned());
-        // Invalidate texture so it will be recreated
-        self.texture = None;
-        self.last_index = None;
-        Ok(())
+    /// File names (without extension) for the regions, in order. Two names can sanitize to the same
+    /// file; later ones get a numeric suffix instead of overwriting, and are listed in the second value.
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn region_file_names(&self) -> (Vec<String>, Vec<String>) {
+        let mut used: std::collections::HashSet<String> = std::collections::HashSet::new();
+        let mut clashes: Vec<String> = Vec::new();
+        let names = self
+            .regions
+            .iter()
+            .map(|r| {
+                let base = sanitize_file_name(&r.name);
+                let mut name = base.clone();
+                let mut n = 2;
+                while !used.insert(name.clone()) {
+                    name = format!("{base}_{n}");
+                    n += 1;
+                }
+                if name != base { clashes.push(format!("'{}' -> {name}.png", r.name)); }
+                name
+            })
+            .collect();
+        (names, clashes)
     }
 
-    fn cols(&self) -> usize {
-        if self.atlas_size[0] == 0 { return 0; }
-        self.atlas_size[0] / self.card_width
-    }
+    /// Pick a folder and start writing every region of every card into it as `{index}_{name}.png`.
+    fn export_all_regions_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            if self.regions.is_empty() {
+                self.error = Some("There are no regions to export".to_owned());
+                return;
+            }
+            let Some(dir) = self.file_dialog().pick_folder().inspect(|p| self.remember_dialog_dir(p)) else { return };
+            let (names, clashes) = self.region_file_names();
+            if !clashes.is_empty() {
+                self.warning = Some(format!("Regions with clashing file names were renamed: {}", clashes.join(", ")));
+            }
+            self.error = None;
+            let regions = self.regions.iter().cloned().zip(names).collect();
+            self.batch_export = Some(BatchExport { dir, next: 0, written: 0, regions: Some(regions), group_by_tag: self.export_group_by_tag, skip_empty: self.skip_empty_cards });
+        }
 
-    fn rows(&self) -> usize {
-        if self.atlas_size[1] == 0 { return 0; }
-        self.atlas_size[1] / self.card_height
-    }
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
 
-    fn max_index(&self) -> usize {
-        let c = self.cols();
-        let r = self.rows();
-        if c == 0 || r == 0 { 0 } else { c * r - 1 }
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.error = Some("Exporting regions is not supported on the web build".to_owned());
+        }
     }
 
-    fn make_card_image(&self, index: usize) -> Option<ColorImage> {
-        let atlas = self.atlas.as_ref()?;
-        let cols = self.cols();
-        if cols == 0 { return None; }
-        let col = index % cols;
-        let row = index / cols;
-        if row * self.card_height + self.card_height > self.atlas_size[1] || col * self.card_width + self.card_width > self.atlas_size[0] {
-            return None;
-        }
+    /// Pick a folder and write every region of the current card into it as `{name}.png`.
+    fn export_regions_dialog(&mut self) {
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        {
+            let Some(card) = self.make_card_rgba(self.index) else {
+                self.error = Some(format!("No card at index {} to export regions from", self.index));
+                return;
+            };
+            let Some(dir) = self.file_dialog().pick_folder().inspect(|p| self.remember_dialog_dir(p)) else { return };
 
-        let mut pixels = vec![0u8; self.card_width * self.card_height * 4];
-        for y in 0..self.card_height {
-            for x in 0..self.card_width {
-                let sx = (col * self.card_width + x) as u32;
-                let sy = (row * self.card_height + y) as u32;
-                let p = atlas.get_pixel(sx, sy);
-                let off = (y * self.card_width + x) * 4;
-                pixels[off..off + 4].copy_from_slice(&p.0);
+            let (names, clashes) = self.region_file_names();
+            let (mut written, mut skipped) = (0, 0);
+            for (r, name) in self.regions.iter().zip(&names) {
+                let Some(crop) = Self::crop_region(&card, r) else {
+                    skipped += 1;
+                    continue;
+                };
+                let crop = self.orient(crop);
+                let path = match region_export_dir(&dir, r, self.export_group_by_tag) {
+                    Ok(dir) => dir.join(format!("{name}.png")),
+                    Err(e) => {
+                        self.error = Some(format!("Export failed creating the folder for '{}': {e}", r.name));
+                        return;
+                    }
+                };
+                if let Err(e) = crop.save_with_format(&path, image::ImageFormat::Png) {
+                    self.error = Some(format!("Export failed at '{}': {e}", path.display()));
+                    return;
+                }
+                written += 1;
+            }
+            let mut status = format!("Exported {written} regions of card {} to {}", self.index, dir.display());
+            if skipped > 0 { status.push_str(&format!("; skipped {skipped} outside the card")); }
+            self.status = Some(status);
+            if !clashes.is_empty() {
+                self.warning = Some(format!("Regions with clashing file names were renamed: {}", clashes.join(", ")));
             }
         }
-        Some(ColorImage::from_rgba_unmultiplied([self.card_width, self.card_height], &pixels))
-    }
 
-    fn ensure_texture(&mut self, ctx: &egui::Context) {
-        if self.last_index == Some(self.index) { return; }
-        self.texture = None;
-        self.last_index = None;
+        #[cfg(target_os = "android")]
+        {
+            self.error = Some("File dialogs are not supported on Android".to_owned());
+        }
 
-        if let Some(img) = self.make_card_image(self.index) {
-            let tex = ctx.load_texture(
-                "card_preview",
-                img,
-                TextureOptions::NEAREST,
-            );
-            self.texture = Some(tex);
-            self.last_index = Some(self.index);
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.error = Some("Exporting regions is not supported on the web build".to_owned());
         }
     }
+
+    /// Keep the selected region's crop texture in sync with the region and the card's place in the atlas.
+    fn ensure_region_preview(&mut self, ctx: &egui::Context) {
+        let Some(r) = self.selected_region.and_then(|i| self.regions.get(i)) else {
+            self.previews.region_preview = None;
+            return;
+        };
+        let key: RegionPreviewKey = (self.card_cell(), [r.x, r.y, r.width, r.height]);
+        if self.previews.region_preview.as_ref().is_some_and(|(k, _)| *k == key) { return; }
+
+        self.previews.region_preview = self
+            .make_card_rgba(self.index)
+            .and_then(|card| Self::crop_region(&card, r))
+            .map(|img| {
+                let size = [img.width() as usize, img.height() as usize];
+                let img = ColorImage::from_rgba_unmultiplied(size, img.as_raw());
+                (key, ctx.load_texture("region_preview", img, TextureOptions::NEAREST))
+            });
+    }
 }
 
 impl eframe::App for TemplateApp {
     /// Called by the framework to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.stash_working_regions();
         eframe::set_value(storage, eframe::APP_KEY, self);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Window title follows the loaded atlas
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let file = self.atlas.as_ref().and(self.atlas_path.as_deref()).map(|p| {
+                Path::new(p).file_name().map_or_else(|| p.to_owned(), |f| f.to_string_lossy().to_string())
+            });
+            let title = match file {
+                Some(f) => format!("{WINDOW_TITLE} — {f}"),
+                None => WINDOW_TITLE.to_owned(),
+            };
+            if self.window_title != title {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+                self.window_title = title;
+            }
+        }
+
+        // Track the window geometry so `save` stores where the window was left
+        #[cfg(not(target_arch = "wasm32"))]
+        ctx.input(|i| {
+            let vp = i.viewport();
+            if vp.minimized != Some(true) && vp.fullscreen != Some(true) {
+                if let Some(r) = vp.inner_rect { self.window_size = [r.width(), r.height()]; }
+                if let Some(r) = vp.outer_rect { self.window_pos = Some([r.min.x, r.min.y]); }
+            }
+        });
+
         // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
@@ -345,6 +4244,74 @@ impl eframe::App for TemplateApp {
             return;
         }
 
+        if self.prompts.confirm_reset {
+            let modal = egui::Modal::new(egui::Id::new("confirm_reset")).show(ctx, |ui| {
+                ui.heading("Reset app?");
+                ui.label("All settings, card sets and regions (of every atlas) are lost. This cannot be undone.");
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").clicked() {
+                        self.reset_app();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.prompts.confirm_reset = false;
+                    }
+                });
+            });
+            if modal.should_close() {
+                self.prompts.confirm_reset = false;
+            }
+        }
+
+        if self.prompts.confirm_clear {
+            let modal = egui::Modal::new(egui::Id::new("confirm_clear")).show(ctx, |ui| {
+                let n = self.regions.len();
+                ui.heading("Clear all regions?");
+                ui.label(format!("{n} region{} will be removed (Ctrl+Z brings them back).", if n == 1 { "" } else { "s" }));
+                ui.horizontal(|ui| {
+                    if ui.button("Clear").clicked() {
+                        self.push_undo();
+                        self.regions.clear();
+                        self.clear_selection();
+                        self.prompts.confirm_clear = false;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.prompts.confirm_clear = false;
+                    }
+                });
+            });
+            if modal.should_close() {
+                self.prompts.confirm_clear = false;
+            }
+        }
+
+        self.command_palette_ui(ctx);
+        self.handle_nav_keys(ctx);
+        self.handle_pending_region_keys(ctx);
+        self.step_slideshow(ctx);
+        self.refresh_empty_cards(ctx);
+        // With regions selected the arrow keys move them; PageUp/PageDown/Home/End still change cards
+        if self.show_regions_panel && !self.card_transformed() && !self.selected_regions.is_empty() {
+            self.auto_advance.nav_repeat = None;
+            self.handle_region_nudge(ctx);
+        } else {
+            self.handle_nav_key_repeat(ctx);
+        }
+        self.step_batch_export(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_atlas_load(ctx);
+        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+        self.update_atlas_watch(ctx);
+        self.handle_undo_shortcuts(ctx);
+        if !ctx.wants_keyboard_input() && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F2)) {
+            self.show_regions_panel = !self.show_regions_panel;
+        }
+        if self.show_regions_panel {
+            self.handle_region_paste(ctx);
+            if !ctx.wants_keyboard_input() && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Delete)) {
+                self.delete_selected_regions();
+            }
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
 
@@ -353,6 +4320,20 @@ impl eframe::App for TemplateApp {
                 let is_web = cfg!(target_arch = "wasm32");
                 if !is_web {
                     ui.menu_button("File", |ui| {
+                        let mut open_recent = None;
+                        ui.add_enabled_ui(!self.recent_atlases.is_empty(), |ui| {
+                            ui.menu_button("Recent", |ui| {
+                                for path in &self.recent_atlases {
+                                    if ui.button(path).clicked() {
+                                        open_recent = Some(path.clone());
+                                    }
+                                }
+                            });
+                        });
+                        if let Some(path) = open_recent {
+                            self.open_recent_atlas(&path);
+                        }
+                        ui.separator();
                         if ui.button("Quit").clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
@@ -360,143 +4341,47 @@ impl eframe::App for TemplateApp {
                     ui.add_space(16.0);
                 }
 
-                egui::widgets::global_theme_preference_buttons(ui);
-            });
-        });
-
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            if self.show_regions_panel {
-                egui::SidePanel::right("regions_panel").resizable(true).default_width(260.0).show(ctx, |ui| {
-                ui.heading("Regions");
-                ui.separator();
-
-                let mut to_delete: Option<usize> = None;
-
-                if let Some([px, py, pw, ph]) = self.pending_region {
-                    ui.label("New region pending:");
-                    ui.horizontal(|ui| {
-                        ui.label(format!("{}×{} @ {},{}", pw, ph, px, py));
-                        if ui.button("Add").clicked() {
-                            self.regions.push(Region { name: self.new_region_name.clone(), x: px, y: py, width: pw, height: ph });
-                            self.selected_region = Some(self.regions.len()-1);
-                            self.pending_region = None;
-                            self.new_region_name.clear();
-                        }
-                        if ui.button("Cancel").clicked() {
-                            self.pending_region = None;
-                            self.new_region_name.clear();
-                        }
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Name:");
-                        ui.add(egui::TextEdit::singleline(&mut self.new_region_name));
-                    });
-                    ui.separator();
-                } else {
-                    ui.label("No pending region.");
-                    ui.separator();
-                }
-
-                ui.label("Saved regions:");
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    for (i, r) in self.regions.iter().enumerate() {
-                        ui.horizontal(|ui| {
-                            let selected = self.selected_region == Some(i);
-                            if ui.selectable_label(selected, &r.name).clicked() {
-                                self.selected_region = Some(i);
-                            }
-                            ui.label(format!("{}x{} @ {},{}", r.width, r.height, r.x, r.y));
-                            if ui.small_button("Delete").clicked() {
-                                to_delete = Some(i);
-                            }
-                        });
+                ui.menu_button("Settings", |ui| {
+                    if ui.button("Export settings...").on_hover_text("Save presets, card sets, regions and options to a file").clicked() {
+                        self.export_settings_dialog();
                     }
-                });
-
-                if let Some(i) = to_delete {
-                    if i < self.regions.len() {
-                        self.regions.remove(i);
-                        if self.selected_region == Some(i) { self.selected_region = None; }
-                    }
-                }
-
-                ui.add_space(8.0);
-                ui.horizontal(|ui| {
-                    if ui.button("Clear All").clicked() {
-                        self.regions.clear();
-                        self.selected_region = None;
+                    if ui.button("Import settings...").on_hover_text("Replace everything with settings exported earlier").clicked() {
+                        self.import_settings_dialog();
                     }
-                    if ui.button("Save...").clicked() {
-                        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
-                        {
-                            if let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).save_file() {
-                                // New format: include the card/image size alongside regions
-                                #[derive(serde::Serialize)]
-                                struct RegionsFile<'a> {
-                                    image_size: [usize; 2],
-                                    regions: &'a [Region],
-                                }
-                                let file = RegionsFile { image_size: [self.card_width, self.card_height], regions: &self.regions };
-                                if let Ok(s) = serde_json::to_string_pretty(&file) {
-                                    let _ = std::fs::write(path, s);
-                                }
-                            }
-                        }
-
-                        #[cfg(target_os = "android")]
-                        {
-                            self.error = Some("File dialogs are not supported on Android".to_owned());
-                        }
+                    ui.separator();
+                    if ui.button("Reset app...").on_hover_text("Forget all settings and regions and start over").clicked() {
+                        self.prompts.confirm_reset = true;
                     }
-                    if ui.button("Load...").clicked() {
-                        #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
-                        {
-                            if let Some(path) = FileDialog::new().add_filter("JSON", &["json"]).pick_file() {
-                                match std::fs::read_to_string(&path) {
-                                    Ok(s) => {
-                                        // Try new format first (object with image_size + regions), otherwise fall back to old Vec<Region>
-                                        #[derive(serde::Deserialize)]
-                                        struct RegionsFile {
-                                            image_size: [usize; 2],
-                                            regions: Vec<Region>,
-                                        }
+                });
+                ui.add_space(16.0);
 
-                                        if let Ok(f) = serde_json::from_str::<RegionsFile>(&s) {
-                                            self.regions = f.regions;
-                                            self.selected_region = None;
-                                            // Update card size to match saved file
-                                            self.card_width = f.image_size[0].max(1);
-                                            self.card_height = f.image_size[1].max(1);
-                                            self.selected_preset = None;
-                                            self.texture = None; // invalidate preview so it will be recreated
-                                            self.last_index = None;
-                                        } else if let Ok(v) = serde_json::from_str::<Vec<Region>>(&s) {
-                                            // Old format
-                                            self.regions = v;
-                                            self.selected_region = None;
-                                        } else {
-                                            self.error = Some("Failed to parse regions file: unknown format".to_owned());
-                                        }
-                                    }
-                                    Err(e) => { self.error = Some(format!("Failed to read regions file: {}", e)); },
-                                }
-                            }
-                        }
+                egui::widgets::global_theme_preference_buttons(ui);
+            });
+        });
 
-                        #[cfg(target_os = "android")]
-                        {
-                            self.error = Some("File dialogs are not supported on Android".to_owned());
-                        }
-                    }
-                });
+        if self.show_regions_panel {
+            self.ensure_region_preview(ctx);
+            let panel = egui::SidePanel::right("regions_panel").resizable(true).default_width(self.regions_panel_width).show(ctx, |ui| {
+                self.regions_panel_ui(ui);
             });
-            }
+            self.regions_panel_width = panel.response.rect.width();
         }
 
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            self.status_bar_ui(ui);
+        });
 
+        if self.show_filmstrip {
+            egui::TopBottomPanel::bottom("filmstrip").resizable(false).show(ctx, |ui| {
+                self.filmstrip_ui(ui);
+            });
+        }
 
-
+        if self.show_notes {
+            egui::SidePanel::left("notes_panel").resizable(true).show(ctx, |ui| {
+                self.notes_ui(ui);
+            });
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             // The central panel — Atlas Viewer
@@ -513,112 +4398,304 @@ impl eframe::App for TemplateApp {
                 ui.horizontal(|ui| {
                     ui.label(self.atlas_path.as_deref().unwrap_or("(none)"));
                     ui.add_space(12.0);
-                    // Atlas presets combo box
+                    // Bundled sample atlases (fetched from the deployment on web, read from disk on native)
                     egui::ComboBox::from_id_salt("atlas_presets").selected_text(
-                        self.selected_atlas.and_then(|i| ATLAS_PRESETS.get(i).map(|(n,_,_,_)| *n)).unwrap_or("Select preset")
+                        self.selected_atlas.and_then(|i| ATLAS_PRESETS.get(i).map(|(n,_,_,_)| *n)).unwrap_or("Sample atlas")
                     ).show_ui(ui, |ui| {
-                        for (i, (name, path, w, h)) in ATLAS_PRESETS.iter().enumerate() {
+                        for (i, (name, _, _, _)) in ATLAS_PRESETS.iter().enumerate() {
                             if ui.selectable_label(self.selected_atlas == Some(i), *name).clicked() {
-                                self.selected_atlas = Some(i);
-                                // Update card sizes to match preset
-                                self.card_width = *w;
-                                self.card_height = *h;
-                                self.selected_preset = None;
-                                self.texture = None;
-                                self.last_index = None;
-
-                                // Load the asset: on native we can read directly, on wasm it will request fetch
-                                #[cfg(not(target_arch = "wasm32"))]
-                                {
-                                    if let Err(e) = self.load_atlas(Path::new(path)) {
-                                        self.error = Some(e);
-                                    } else {
-                                        self.error = None;
-                                    }
-                                }
-
-                                #[cfg(target_arch = "wasm32")]
-                                {
-                                    crate::file_picker::request_asset(path);
-                                }
+                                self.open_atlas_preset(i);
                             }
                         }
                     });
                 });
                 if ui.button("Open...").clicked() {
-                    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
-                    {
-                        if let Some(path) = FileDialog::new().add_filter("Image", &["png", "jpg", "jpeg"]).pick_file() {
-                            match self.load_atlas(&path) {
-                                Ok(()) => self.error = None,
-                                Err(e) => self.error = Some(e),
-                            }
+                    self.open_atlas_dialog();
+                }
+                #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+                if ui.button("Open URL...").on_hover_text("Download an atlas image over http(s)").clicked() {
+                    self.prompts.url_input = Some(String::new());
+                }
+                if ui.button("Reload").clicked() {
+                    self.reload_atlas();
+                }
+                #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+                ui.checkbox(&mut self.watch_atlas, "Watch file")
+                    .on_hover_text("Reload the atlas whenever its file changes on disk (card and regions are kept)");
+            });
+
+            #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+            if let Some(mut url) = self.prompts.url_input.take() {
+                ui.horizontal(|ui| {
+                    ui.label("URL:");
+                    let edit = ui.add(egui::TextEdit::singleline(&mut url).hint_text("https://example.com/atlas.png").desired_width(360.0));
+                    let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let url = url.trim().to_owned();
+                    if ui.add_enabled(is_url(&url), egui::Button::new("Load")).clicked() || (enter && is_url(&url)) {
+                        match self.load_atlas(Path::new(&url)) {
+                            Ok(()) => self.error = None,
+                            Err(e) => self.error = Some(e),
                         }
+                    } else if !ui.button("Cancel").clicked() {
+                        self.prompts.url_input = Some(url);
                     }
+                });
+            }
 
-                    #[cfg(target_os = "android")]
-                    {
-                        self.error = Some("File dialogs are not supported on Android".to_owned());
+            if let Some(warning) = self.warning.clone() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::YELLOW, warning);
+                    if ui.small_button("Dismiss").clicked() {
+                        self.warning = None;
                     }
+                });
+            }
 
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        crate::file_picker::open_image_picker();
+            // The last session's atlas is gone: offer to find it rather than just failing
+            if let Some(missing) = self.atlas_jobs.missing_atlas.clone() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("The atlas from the last session, '{missing}', was not found. Was it moved or renamed?"),
+                    );
+                    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+                    if ui.button("Locate...").on_hover_text("Pick the file at its new location; its regions come along").clicked() {
+                        self.locate_missing_atlas(&missing);
                     }
-                }
-                if ui.button("Reload").clicked() {
-                    if let Some(p) = self.atlas_path.clone() {
-                        if let Err(e) = self.load_atlas(Path::new(&p)) {
-                            self.error = Some(e);
-                        } else {
-                            self.error = None;
-                        }
+                    if ui.button("Dismiss").clicked() {
+                        self.atlas_jobs.missing_atlas = None;
                     }
-                }
-            });
+                });
+            }
+
+            // Card size suggested by the atlas metadata: ask before overriding the current size
+            if let Some([hw, hh]) = self.prompts.card_size_hint {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "Atlas metadata suggests {}×{} cards ({} cols × {} rows).",
+                        hw, hh, self.atlas_size[0] / hw, self.atlas_size[1] / hh
+                    ));
+                    if ui.button("Apply").clicked() {
+                        self.set_card_size(hw, hh, "atlas metadata");
+                        self.selected_preset = None;
+                        self.prompts.card_size_hint = None;
+                    }
+                    if ui.button("Ignore").clicked() {
+                        self.prompts.card_size_hint = None;
+                    }
+                });
+            }
+
+            // Saving would convert a regions file written in another format: confirm first
+            #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+            if let Some((path, format)) = self.prompts.pending_regions_overwrite.clone() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::YELLOW, format!(
+                        "{} is an existing {format}; saving replaces it with a {}.",
+                        path.display(),
+                        regions_file_format_name(false, self.save_coordinate_space)
+                    ));
+                    if ui.button("Overwrite").clicked() {
+                        self.prompts.pending_regions_overwrite = None;
+                        self.write_regions_file(&path);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.prompts.pending_regions_overwrite = None;
+                    }
+                });
+            }
+
+            self.card_sets_ui(ui);
 
             // Card size controls + presets
             ui.horizontal(|ui| {
                 ui.label("Format:");
+                let formats = self.card_formats();
                 let selected_text = self
                     .selected_preset
-                    .and_then(|i| CARD_FORMATS.get(i).map(|(n,_,_)| *n))
+                    .and_then(|i| formats.get(i).map(|(n,_,_)| n.as_str()))
                     .unwrap_or("Custom");
 
                 egui::ComboBox::from_id_salt("card_format").selected_text(selected_text).show_ui(ui, |ui| {
-                    for (i, (name, w, h)) in CARD_FORMATS.iter().enumerate() {
-                        if ui.selectable_label(self.selected_preset == Some(i), *name).clicked() {
-                            self.selected_preset = Some(i);
-                            self.card_width = *w;
-                            self.card_height = *h;
-                            self.texture = None;
-                            self.last_index = None;
-                            if self.index > self.max_index() { self.index = self.max_index(); }
+                    // Pinned presets first (in pin order), then the remaining ones in list order
+                    let pinned: Vec<usize> = self
+                        .pinned_presets
+                        .iter()
+                        .filter_map(|p| formats.iter().position(|(name, _, _)| name == p))
+                        .collect();
+                    let unpinned = (0..formats.len()).filter(|i| !pinned.contains(i));
+                    let mut toggle_pin: Option<usize> = None;
+                    let mut delete: Option<usize> = None;
+                    for (n, i) in pinned.iter().copied().chain(unpinned).enumerate() {
+                        if n == pinned.len() && n > 0 { ui.separator(); }
+                        let Some((name, w, h)) = formats.get(i) else { continue };
+                        ui.horizontal(|ui| {
+                            let is_pinned = pinned.contains(&i);
+                            if ui.selectable_label(is_pinned, "📌").on_hover_text(if is_pinned { "Unpin" } else { "Pin to top" }).clicked() {
+                                toggle_pin = Some(i);
+                            }
+                            if ui.selectable_label(self.selected_preset == Some(i), name).clicked() {
+                                self.selected_preset = Some(i);
+                                if self.rescale_regions_with_card && !self.regions.is_empty() {
+                                    self.push_undo();
+                                    self.rescale_regions((self.card_width, self.card_height), (*w, *h));
+                                }
+                                self.card_width = *w;
+                                self.card_height = *h;
+                                self.texture = None;
+                                self.last_index = None;
+                                if self.index > self.max_index() { self.index = self.max_index(); }
+                            }
+                            // Built-ins cannot be deleted
+                            if i >= CARD_FORMATS.len() && ui.small_button("🗑").on_hover_text("Delete this preset").clicked() {
+                                delete = Some(i);
+                            }
+                        });
+                    }
+                    if let Some((name, _, _)) = toggle_pin.and_then(|i| formats.get(i)) {
+                        if let Some(pos) = self.pinned_presets.iter().position(|p| p == name) {
+                            self.pinned_presets.remove(pos);
+                        } else {
+                            self.pinned_presets.push(name.clone());
                         }
                     }
+                    if let Some(i) = delete
+                        && let Some(user) = i.checked_sub(CARD_FORMATS.len())
+                        && user < self.user_presets.len()
+                    {
+                        let (name, _, _) = self.user_presets.remove(user);
+                        self.pinned_presets.retain(|p| *p != name);
+                        // indices after the deleted preset shift down
+                        self.selected_preset = match self.selected_preset {
+                            Some(s) if s == i => None,
+                            Some(s) if s > i => Some(s - 1),
+                            other => other,
+                        };
+                    }
                     if ui.selectable_label(self.selected_preset.is_none(), "Custom").clicked() {
                         self.selected_preset = None;
                     }
                 });
 
+                match &mut self.prompts.new_preset_name {
+                    None => {
+                        if ui.button("Save as preset...").on_hover_text("Save the current card size as a format preset").clicked() {
+                            self.prompts.new_preset_name = Some(String::new());
+                        }
+                    }
+                    Some(name) => {
+                        let resp = ui.add(egui::TextEdit::singleline(name).desired_width(140.0).hint_text("Preset name"));
+                        let entered = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        let name = name.clone();
+                        if entered || ui.button("Save").clicked() {
+                            self.save_user_preset(&name);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.prompts.new_preset_name = None;
+                        }
+                    }
+                }
+
+                if ui.button("Detect grid").on_hover_text("Guess the card size from the atlas").clicked() {
+                    match self.detect_grid() {
+                        Some((w, h)) => {
+                            self.set_card_size(w, h, "grid detection");
+                            self.selected_preset = None;
+                            self.status = Some(format!("Detected {}×{} cards ({} cols × {} rows)", w, h, self.cols(), self.rows()));
+                        }
+                        None => {
+                            self.error = Some(
+                                "Could not detect a card grid: no regularly spaced card edges or gutters found. Set the card size manually.".to_owned(),
+                            );
+                        }
+                    }
+                }
+
                 ui.separator();
 
                 ui.label("Card width:");
                 let mut w = self.card_width as i64;
-                ui.add(egui::DragValue::new(&mut w).range(1..=4096));
+                let rw = ui.add(egui::DragValue::new(&mut w).range(1..=MAX_CARD_SIDE as i64));
                 ui.label("Card height:");
                 let mut h = self.card_height as i64;
-                ui.add(egui::DragValue::new(&mut h).range(1..=4096));
+                let rh = ui.add(egui::DragValue::new(&mut h).range(1..=MAX_CARD_SIDE as i64));
+                ui.checkbox(&mut self.rescale_regions_with_card, "Rescale regions")
+                    .on_hover_text("Scale the regions proportionally when the card size changes instead of keeping their pixel coordinates");
 
                 let changed = (w as usize != self.card_width) || (h as usize != self.card_height);
+                let old = (self.card_width, self.card_height);
                 self.card_width = w.max(1) as usize;
                 self.card_height = h.max(1) as usize;
+                // Rescale from the regions as they were before the drag/typing started, so rounding
+                // does not accumulate over many small steps; the whole change is one undo step
+                if changed && self.rescale_regions_with_card {
+                    let (base_size, base) = self.history.card_resize_base.get_or_insert_with(|| (old, self.regions.clone())).clone();
+                    self.regions = base;
+                    self.rescale_regions(base_size, (self.card_width, self.card_height));
+                }
+                let active = rw.dragged() || rw.has_focus() || rh.dragged() || rh.has_focus();
+                if !active
+                    && let Some((_, base)) = self.history.card_resize_base.take()
+                    && base != self.regions
+                {
+                    self.push_undo_snapshot(base);
+                }
                 if changed {
                     // If user manually changes size, treat as custom
                     self.selected_preset = None;
                     self.texture = None;
                     self.last_index = None;
-                    // clamp index
+                    // clamp index
+                    if self.index > self.max_index() { self.index = self.max_index(); }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let before = [self.margin_x, self.margin_y, self.spacing_x, self.spacing_y];
+                let offset_before = [self.offset_x, self.offset_y];
+                ui.label("Margin x:");
+                ui.add(egui::DragValue::new(&mut self.margin_x).range(0..=MAX_CARD_SIDE));
+                ui.label("y:");
+                ui.add(egui::DragValue::new(&mut self.margin_y).range(0..=MAX_CARD_SIDE));
+                ui.separator();
+                ui.label("Spacing x:");
+                ui.add(egui::DragValue::new(&mut self.spacing_x).range(0..=MAX_CARD_SIDE));
+                ui.label("y:");
+                ui.add(egui::DragValue::new(&mut self.spacing_y).range(0..=MAX_CARD_SIDE));
+                ui.separator();
+                let side = MAX_CARD_SIDE as i32;
+                ui.label("Offset x:").on_hover_text("Shift the whole grid by a few pixels; negative moves it left / up");
+                ui.add(egui::DragValue::new(&mut self.offset_x).range(-side..=side));
+                ui.label("y:");
+                ui.add(egui::DragValue::new(&mut self.offset_y).range(-side..=side));
+                ui.separator();
+                let pitch_before = self.card_pitch;
+                let mut fractional = self.card_pitch.is_some();
+                ui.checkbox(&mut fractional, "Fractional size")
+                    .on_hover_text("Cards of a rescaled atlas that are not a whole number of pixels wide; each card is cut at its rounded position so the last columns stay aligned");
+                match (fractional, &mut self.card_pitch) {
+                    (false, pitch) => *pitch = None,
+                    (true, None) => self.card_pitch = Some([self.card_width as f32, self.card_height as f32]),
+                    (true, Some(pitch)) => {
+                        // A size typed or picked from a preset replaces the fractional one
+                        if [pitch[0].round() as usize, pitch[1].round() as usize] != [self.card_width, self.card_height] {
+                            *pitch = [self.card_width as f32, self.card_height as f32];
+                        }
+                        ui.add(egui::DragValue::new(&mut pitch[0]).range(1.0..=MAX_CARD_SIDE as f32).speed(0.1).max_decimals(3));
+                        ui.label("×");
+                        ui.add(egui::DragValue::new(&mut pitch[1]).range(1.0..=MAX_CARD_SIDE as f32).speed(0.1).max_decimals(3));
+                        // Keep the whole-pixel size (what gets cut out) in step with the pitch
+                        self.card_width = (pitch[0].round() as usize).max(1);
+                        self.card_height = (pitch[1].round() as usize).max(1);
+                    }
+                }
+                if before != [self.margin_x, self.margin_y, self.spacing_x, self.spacing_y]
+                    || offset_before != [self.offset_x, self.offset_y]
+                    || pitch_before != self.card_pitch
+                {
+                    self.previews.thumbnails.clear();
+                    self.texture = None;
+                    self.last_index = None;
                     if self.index > self.max_index() { self.index = self.max_index(); }
                 }
             });
@@ -627,87 +4704,548 @@ impl eframe::App for TemplateApp {
                 ui.label("Card index:");
                 let mut idx = self.index as i64;
                 ui.add(egui::DragValue::new(&mut idx).range(0..=self.max_index() as i64));
+                let cols = self.cols().max(1);
+                ui.label(format!("(row {}, col {})", self.index / cols, self.index % cols));
+                if let Some(note) = self.card_notes.get(&self.index)
+                    && ui.small_button("📝").on_hover_text(note.as_str()).clicked()
+                {
+                    self.show_notes = true;
+                }
+                let jump = ui.add(
+                    egui::TextEdit::singleline(&mut self.inputs.jump_input)
+                        .desired_width(60.0)
+                        .hint_text("row,col"),
+                ).on_hover_text("Type an index or row,col and press Enter");
+                if jump.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    match parse_card_ref(&self.inputs.jump_input, self.cols(), self.rows()) {
+                        Ok(i) => {
+                            idx = i as i64;
+                            self.inputs.jump_input.clear();
+                            self.warning = None;
+                        }
+                        Err(e) => self.warning = Some(e),
+                    }
+                }
                 if ui.button("Prev").clicked() {
-                    idx = (idx - 1).max(0);
+                    idx = self.step_index(-1) as i64;
                 }
                 if ui.button("Next").clicked() {
-                    let max = self.max_index() as i64;
-                    idx = (idx + 1).min(max);
+                    idx = self.step_index(1) as i64;
+                }
+                ui.checkbox(&mut self.skip_empty_cards, "Skip empty")
+                    .on_hover_text("Prev/Next, the arrow keys and \"Export all\" pass over fully transparent cards");
+                let playing = self.auto_advance.slideshow_next.is_some();
+                if ui.selectable_label(playing, if playing { "⏸ Pause" } else { "▶ Play" })
+                    .on_hover_text("Step through the cards automatically")
+                    .clicked()
+                {
+                    self.auto_advance.slideshow_next = (!playing).then(|| ui.input(|i| i.time) + f64::from(self.slideshow_interval));
+                }
+                ui.add(egui::Slider::new(&mut self.slideshow_interval, 0.1..=10.0).logarithmic(true).suffix(" s"))
+                    .on_hover_text("How long each card is shown while playing");
+                if ui.button("Export card...").on_hover_text("Save the current card as a PNG").clicked() {
+                    self.export_card_dialog();
+                }
+                if ui.button("Copy card").on_hover_text("Copy the current card image to the clipboard").clicked() {
+                    self.copy_card_to_clipboard();
+                }
+                if self.batch_export.is_some() {
+                    if ui.button("Cancel export").clicked() {
+                        self.batch_export = None;
+                        self.status = Some("Export cancelled".to_owned());
+                    }
+                } else if ui.button("Export all...").on_hover_text("Save every card as a PNG into a folder").clicked() {
+                    self.export_all_dialog();
                 }
                 let max = self.max_index() as i64;
                 idx = idx.clamp(0, max);
                 self.index = idx as usize;
 
                 ui.separator();
-                ui.label(format!("Atlas: {}x{} | cols: {} rows: {} | max index: {}", self.atlas_size[0], self.atlas_size[1], self.cols(), self.rows(), self.max_index()));
+                ui.label("Key repeat:").on_hover_text("Hold Left/Right to step through cards");
+                ui.add(egui::DragValue::new(&mut self.key_repeat_delay).range(0.05..=2.0).speed(0.01).suffix(" s delay"));
+                ui.add(egui::DragValue::new(&mut self.key_repeat_rate).range(1.0..=60.0).speed(0.1).suffix(" /s"));
+
+            });
+
+            // Show/hide Regions panel
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_regions_panel, "Show regions panel").on_hover_text("F2");
+                if self.show_regions_panel {
+                    ui.separator();
+                    ui.label("Outline:");
+                    ui.add(egui::Slider::new(&mut self.overlay_stroke_width, 0.5..=8.0).suffix(" px"))
+                        .on_hover_text("Width of region outlines; raise it for large atlases viewed zoomed out");
+                    ui.label("Fill:");
+                    ui.add(egui::Slider::new(&mut self.overlay_fill_alpha, 0..=255))
+                        .on_hover_text("Opacity of the selected and pending region fill");
+                    ui.label("Drag threshold:");
+                    ui.add(egui::Slider::new(&mut self.drag_threshold, 1.0..=20.0).suffix(" px"))
+                        .on_hover_text("How far the pointer must move before a press draws a region instead of clicking; raise it for touchscreens");
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_grid, "Grid overview")
+                    .on_hover_text("Show the whole atlas with card boundaries; click a cell to select it");
+                ui.checkbox(&mut self.show_filmstrip, "Filmstrip")
+                    .on_hover_text("Show thumbnails of the neighbouring cards along the bottom");
+                ui.checkbox(&mut self.show_notes, "Notes")
+                    .on_hover_text("Show a panel for notes on the current card");
+                ui.label(format!("Zoom: {:.0}%", self.zoom * 100.0))
+                    .on_hover_text("Scroll to zoom; drag with the middle mouse button or Space + drag to pan");
+                let before = self.view_mode;
+                egui::ComboBox::from_id_salt("view_mode")
+                    .selected_text(self.view_mode.label())
+                    .width(80.0)
+                    .show_ui(ui, |ui| {
+                        for mode in ViewMode::ALL {
+                            ui.selectable_value(&mut self.view_mode, mode, mode.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Base preview size; except for Fit the wheel scrolls an overflowing card and Alt+wheel zooms");
+                if self.view_mode != before {
+                    self.zoom = 1.0;
+                    // scrolling modes start at the top-left corner (the scroll clamp pulls this back to the card edges)
+                    self.pan_offset = if self.view_mode == ViewMode::Fit { egui::Vec2::ZERO } else { egui::Vec2::splat(f32::MAX) };
+                }
+                egui::ComboBox::from_id_salt("preview_background")
+                    .selected_text(self.preview_background.label())
+                    .width(100.0)
+                    .show_ui(ui, |ui| {
+                        for bg in PreviewBackground::ALL {
+                            ui.selectable_value(&mut self.preview_background, bg, bg.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Background behind transparent parts of the card");
+                if self.preview_background == PreviewBackground::Custom {
+                    ui.color_edit_button_srgb(&mut self.background_color);
+                }
+                if ui.button("Fit").clicked() {
+                    self.view_mode = ViewMode::Fit;
+                    self.zoom = 1.0;
+                    self.pan_offset = egui::Vec2::ZERO;
+                }
+                let before = self.texture_filter;
+                ui.selectable_value(&mut self.texture_filter, TextureFilter::Nearest, "Nearest")
+                    .on_hover_text("Sharp pixels, best for inspecting details");
+                ui.selectable_value(&mut self.texture_filter, TextureFilter::Linear, "Linear")
+                    .on_hover_text("Smooth scaling, best for viewing card art");
+                if self.texture_filter != before {
+                    self.texture = None;
+                    self.last_index = None;
+                }
+                ui.separator();
+                let before = (self.card_rotation, self.flip_h, self.flip_v);
+                egui::ComboBox::from_id_salt("card_rotation")
+                    .selected_text(format!("{}°", self.card_rotation))
+                    .width(56.0)
+                    .show_ui(ui, |ui| {
+                        for deg in [0, 90, 180, 270] {
+                            ui.selectable_value(&mut self.card_rotation, deg, format!("{deg}°"));
+                        }
+                    })
+                    .response
+                    .on_hover_text("Rotate the viewed and exported card clockwise");
+                ui.checkbox(&mut self.flip_h, "Flip H").on_hover_text("Mirror the viewed and exported card left-right");
+                ui.checkbox(&mut self.flip_v, "Flip V").on_hover_text("Mirror the viewed and exported card top-bottom");
+                if (self.card_rotation, self.flip_h, self.flip_v) != before {
+                    self.texture = None;
+                    self.last_index = None;
+                    self.previews.thumbnails.clear();
+                }
+                if ui.checkbox(&mut self.show_bleed, "Bleed")
+                    .on_hover_text(format!("Show {BLEED_PX} px of the neighbouring cards around the card to check the crop"))
+                    .changed()
+                {
+                    self.last_index = None;
+                }
+                ui.separator();
+                let mut snap = self.snap_step > 0;
+                if ui.checkbox(&mut snap, "Snap").on_hover_text("Snap region edges to a grid of card pixels").changed() {
+                    self.snap_step = if snap { DEFAULT_SNAP_STEP } else { 0 };
+                }
+                if self.snap_step > 0 {
+                    ui.add(egui::DragValue::new(&mut self.snap_step).range(1..=256).suffix(" px"));
+                }
+                ui.separator();
+                ui.toggle_value(&mut self.show_loupe, "Loupe")
+                    .on_hover_text(format!("Show the pixels under the cursor magnified {LOUPE_ZOOM}×"));
+                if ui.toggle_value(&mut self.measure.active, "Measure")
+                    .on_hover_text("Drag on the card to measure distances in card pixels")
+                    .changed()
+                {
+                    self.measure.ends = None;
+                }
             });
 
-            // Show/hide Regions panel (native only)
+            if let Some(status) = &self.status {
+                ui.label(status);
+            }
+
             #[cfg(not(target_arch = "wasm32"))]
-            ui.checkbox(&mut self.show_regions_panel, "Show regions panel");
+            if let Some((path, _)) = &self.atlas_jobs.atlas_loading {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Loading {path}..."));
+                });
+            }
 
             if let Some(err) = &self.error {
                 ui.colored_label(egui::Color32::RED, err);
                 ui.label("Place your atlas image and use Open... to pick it.");
+            } else if self.show_grid {
+                self.atlas_grid_ui(ui);
             } else {
                 // Ensure texture exists / is updated if index changed
                 self.ensure_texture(ctx);
 
-                if let Some(tex) = &self.texture {
+                // TextureHandle clones are cheap and free `self` for mutation inside the closure
+                if let Some(tex) = self.texture.clone() {
                     ui.vertical_centered(|ui| {
-                        // Fit the preview into available space while preserving aspect ratio
+                        // Fit the preview into available space while preserving aspect ratio,
+                        // then apply the user's zoom/pan on top of the fitted view
                         let avail = ui.available_size();
-                        let cw = self.card_width as f32;
-                        let ch = self.card_height as f32;
+                        // The texture is the oriented card, so a quarter turn swaps width and height
+                        let [cw, ch] = tex.size_vec2().into();
                         // Reserve some space so UI controls remain visible. Allow scaling up to 4x.
                         let max_w = (avail.x - 20.0).max(10.0);
                         let max_h = ((avail.y * 1.0) - 20.0).max(10.0);
-                        let scale_x = max_w / cw;
-                        let scale_y = max_h / ch;
-                        let mut scale = scale_x.min(scale_y);
-                        scale = scale.clamp(0.1, 4.0);
-                        let desired_size = egui::vec2(cw * scale, ch * scale);
-
-                        // Show image and capture response for mouse interactions
-                        let img_widget = egui::Image::new((tex.id(), desired_size));
-                        let resp = ui.add(img_widget.sense(egui::Sense::click_and_drag()));
-                        let img_rect = resp.rect;
-
-                        // Minimal debug: show hovered+clicked. Disabled on wasm builds.
-                        if self.show_regions_panel {
-                            egui::TopBottomPanel::bottom("debug_panel").show(ctx, |ui| {
-                                ui.horizontal_wrapped(|ui| {
-                                    ui.label(format!("hovered: {}", resp.hovered()));
-                                    ui.separator();
-                                    ui.label(format!("contains_pointer: {}", resp.contains_pointer()));
-                                    ui.separator();
-                                    ui.label(format!("pointer_down_on: {}", resp.is_pointer_button_down_on()));
-                                    ui.separator();
-                                    ui.label(format!("interact_pos: {:?}", resp.interact_pointer_pos()));
-                                    ui.separator();
-                                    ui.label(format!("drag_started: {}", resp.drag_started_by(egui::PointerButton::Primary)));
-                                    ui.separator();
-                                    ui.label(format!("dragged: {}", resp.dragged_by(egui::PointerButton::Primary)));
-                                    ui.separator();
-                                    ui.label(format!("drag_stopped: {}", resp.drag_stopped_by(egui::PointerButton::Primary)));
-                                    ui.separator();
-                                    ui.label(format!("clicked: {}", resp.clicked_by(egui::PointerButton::Primary)));
+                        let card_size = egui::vec2(cw, ch);
+                        // Leave room for the bleed around the card when fitting it
+                        let bleed = if self.previews.bleed_texture.is_some() { 2.0 * BLEED_PX as f32 } else { 0.0 };
+                        let fit_scale = self.view_mode.scale(card_size + egui::Vec2::splat(bleed), egui::vec2(max_w, max_h));
+
+                        // The viewport captures mouse interactions; a zoomed-in card is clipped to it
+                        let (viewport, resp) = ui.allocate_exact_size(egui::vec2(max_w, max_h), egui::Sense::click_and_drag());
+
+                        // Scroll wheel zooms around the cursor (Ctrl+scroll stays egui's UI zoom). In the
+                        // modes that let the card overflow it scrolls instead, and Alt+wheel zooms.
+                        let scrolls = self.view_mode != ViewMode::Fit && !ctx.input(|i| i.modifiers.alt);
+                        if let Some(cursor) = resp.hover_pos() {
+                            let (command, delta) = ctx.input(|i| (i.modifiers.command, i.raw_scroll_delta));
+                            // Alt may turn vertical wheel steps into horizontal ones on some platforms
+                            let scroll = if command { 0.0 } else if delta.y != 0.0 { delta.y } else { delta.x };
+                            if scrolls && !command {
+                                self.pan_offset += delta;
+                            } else if scroll != 0.0 {
+                                let old_scale = fit_scale * self.zoom;
+                                let old_min = viewport.center() - card_size * old_scale / 2.0 + self.pan_offset;
+                                let card_pos = (cursor - old_min) / old_scale;
+                                self.zoom = (self.zoom * (scroll * ZOOM_SPEED).exp()).clamp(MIN_ZOOM, MAX_ZOOM);
+                                // keep the card pixel under the cursor in place
+                                let new_scale = fit_scale * self.zoom;
+                                self.pan_offset = cursor - card_pos * new_scale - (viewport.center() - card_size * new_scale / 2.0);
+                            }
+                        }
+
+                        // Middle-mouse drag, or Space + primary drag, pans the view
+                        let space_held = !ctx.wants_keyboard_input() && ctx.input(|i| i.key_down(egui::Key::Space));
+                        if resp.dragged_by(egui::PointerButton::Middle) || (space_held && resp.dragged_by(egui::PointerButton::Primary)) {
+                            self.pan_offset += resp.drag_delta();
+                        }
+
+                        // Center on region: the region fills most of the viewport (within the zoom limits)
+                        if std::mem::take(&mut self.center_on_region)
+                            && let Some([x, y, w, h]) = self
+                                .selected_region
+                                .and_then(|i| self.regions.get(i))
+                                .map(|r| [r.x, r.y, r.width.max(1), r.height.max(1)])
+                        {
+                            let region = egui::vec2(w as f32, h as f32);
+                            self.zoom = (viewport.size() * CENTER_REGION_FILL / (region * fit_scale)).min_elem().clamp(MIN_ZOOM, MAX_ZOOM);
+                            let scale = fit_scale * self.zoom;
+                            self.pan_offset = (card_size / 2.0 - egui::vec2(x as f32, y as f32) - region / 2.0) * scale;
+                        }
+
+                        let scale = fit_scale * self.zoom;
+                        let desired_size = card_size * scale;
+                        // Scrollbars along the bottom and right edges while the card overflows in a scrolling
+                        // view mode: the pan offset runs from +slack (start of the card) to -slack (end)
+                        let slack = ((desired_size - viewport.size()) / 2.0).max(egui::Vec2::ZERO);
+                        let scrolling = self.view_mode != ViewMode::Fit;
+                        let tracks = [
+                            (scrolling && slack.x > 0.0).then(|| {
+                                egui::Rect::from_min_max(egui::pos2(viewport.min.x, viewport.max.y - SCROLLBAR_WIDTH), viewport.max)
+                            }),
+                            (scrolling && slack.y > 0.0).then(|| {
+                                egui::Rect::from_min_max(egui::pos2(viewport.max.x - SCROLLBAR_WIDTH, viewport.min.y), viewport.max)
+                            }),
+                        ];
+                        // (track, thumb) per axis
+                        let scrollbar = |axis: usize, pan: egui::Vec2| {
+                            let track = tracks.get(axis).copied().flatten()?;
+                            let (len, view, slack, pan) = if axis == 0 {
+                                (track.width(), viewport.width(), slack.x, pan.x)
+                            } else {
+                                (track.height(), viewport.height(), slack.y, pan.y)
+                            };
+                            let thumb_len = (len * view / (view + 2.0 * slack)).max(2.0 * SCROLLBAR_WIDTH);
+                            let start = (slack - pan) / (2.0 * slack) * (len - thumb_len);
+                            let thumb = if axis == 0 {
+                                egui::Rect::from_min_size(track.min + egui::vec2(start, 0.0), egui::vec2(thumb_len, track.height()))
+                            } else {
+                                egui::Rect::from_min_size(track.min + egui::vec2(0.0, start), egui::vec2(track.width(), thumb_len))
+                            };
+                            Some((track, thumb, len - thumb_len, slack))
+                        };
+                        let on_scrollbar = ctx.input(|i| i.pointer.press_origin())
+                            .is_some_and(|p| tracks.iter().flatten().any(|t| t.contains(p)));
+                        if on_scrollbar && ctx.input(|i| i.pointer.primary_down()) {
+                            let delta = ctx.input(|i| i.pointer.delta());
+                            for axis in 0..2 {
+                                let Some((track, _, travel, slack)) = scrollbar(axis, self.pan_offset) else { continue };
+                                let pressed_here = ctx.input(|i| i.pointer.press_origin()).is_some_and(|p| track.contains(p));
+                                if pressed_here && travel > 0.0 {
+                                    // moving the thumb by `travel` scrolls the whole card through
+                                    let d = if axis == 0 { delta.x } else { delta.y };
+                                    self.pan_offset[axis] -= d / travel * 2.0 * slack;
+                                }
+                            }
+                        }
+                        // Scrolling stops at the card edges; a card smaller than the viewport stays centered
+                        if scrolling {
+                            self.pan_offset = self.pan_offset.clamp(-slack, slack);
+                        }
+                        let img_rect = egui::Rect::from_min_size(viewport.center() - desired_size / 2.0 + self.pan_offset, desired_size);
+                        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                        if let Some(bleed_tex) = &self.previews.bleed_texture {
+                            // tinted so the neighbours' pixels read as outside the card
+                            let bleed_rect = img_rect.expand(BLEED_PX as f32 * scale);
+                            ui.painter_at(viewport).image(bleed_tex.id(), bleed_rect, uv, egui::Color32::from_rgb(120, 150, 255));
+                        }
+                        self.paint_preview_background(&ui.painter_at(viewport.intersect(img_rect)), img_rect);
+                        ui.painter_at(viewport).image(tex.id(), img_rect, uv, egui::Color32::WHITE);
+                        if self.previews.bleed_texture.is_some() {
+                            ui.painter_at(viewport).rect_stroke(img_rect, 0.0, egui::Stroke::new(1.0, egui::Color32::YELLOW), egui::StrokeKind::Outside);
+                        }
+
+                        // Mini-map in the bottom-right corner while part of the card is out of view;
+                        // pressing or dragging in it centers the view on that spot
+                        let minimap = (!viewport.contains_rect(img_rect)).then(|| {
+                            let size = card_size * (MINIMAP_SIDE / cw.max(ch));
+                            egui::Rect::from_min_size(viewport.right_bottom() - size - egui::vec2(8.0, 8.0), size)
+                        });
+                        let on_minimap = minimap.is_some_and(|m| ctx.input(|i| i.pointer.press_origin()).is_some_and(|p| m.contains(p)));
+                        if on_minimap
+                            && let Some(mini) = minimap
+                            && let Some(pos) = ctx.input(|i| i.pointer.interact_pos()).filter(|_| ctx.input(|i| i.pointer.primary_down()))
+                        {
+                            let card_pos = ((pos - mini.min) / mini.size()).clamp(egui::Vec2::ZERO, egui::Vec2::splat(1.0)) * card_size;
+                            self.pan_offset = desired_size / 2.0 - card_pos * scale;
+                            ctx.request_repaint();
+                        }
+
+                        // Pixel grid when zoomed in far enough to tell pixels apart (only the visible lines)
+                        let visible = viewport.intersect(img_rect);
+                        if scale >= PIXEL_GRID_MIN_SCALE && visible.is_positive() {
+                            let painter = ui.painter_at(visible);
+                            let stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(128).gamma_multiply(0.35));
+                            let first = ((visible.min - img_rect.min) / scale).floor();
+                            let last = ((visible.max - img_rect.min) / scale).ceil();
+                            for x in first.x as usize..=last.x as usize {
+                                painter.vline(img_rect.min.x + x as f32 * scale, visible.y_range(), stroke);
+                            }
+                            for y in first.y as usize..=last.y as usize {
+                                painter.hline(visible.x_range(), img_rect.min.y + y as f32 * scale, stroke);
+                            }
+                        }
+
+                        // Faint snap gridlines (skipped when zoomed out so far that they would merge)
+                        let snap_gap = self.snap_step as f32 * scale;
+                        // Regions (and so snapping) are edited in atlas orientation only
+                        let edit_regions = self.show_regions_panel && !self.card_transformed() && !self.measure.active;
+                        if edit_regions && self.snap_step > 0 && snap_gap >= MIN_SNAP_LINE_GAP {
+                            let painter = ui.painter_at(viewport.intersect(img_rect));
+                            let stroke = egui::Stroke::new(1.0, egui::Color32::from_white_alpha(40));
+                            for x in (self.snap_step..self.card_width).step_by(self.snap_step) {
+                                let sx = img_rect.min.x + x as f32 * scale;
+                                painter.line_segment([egui::pos2(sx, img_rect.min.y), egui::pos2(sx, img_rect.max.y)], stroke);
+                            }
+                            for y in (self.snap_step..self.card_height).step_by(self.snap_step) {
+                                let sy = img_rect.min.y + y as f32 * scale;
+                                painter.line_segment([egui::pos2(img_rect.min.x, sy), egui::pos2(img_rect.max.x, sy)], stroke);
+                            }
+                        }
+
+                        // Snap legend in the top-right corner, so jumping edges have a visible cause;
+                        // clicking it opens the snap settings
+                        let snap_legend = (edit_regions && self.snap_step > 0).then(|| {
+                            let text = egui::RichText::new(format!("Snap: {} px grid", self.snap_step)).small();
+                            let galley = egui::WidgetText::from(text.clone()).into_galley(ui, Some(egui::TextWrapMode::Extend), f32::INFINITY, egui::TextStyle::Small);
+                            let size = galley.size() + 2.0 * ui.spacing().button_padding;
+                            let rect = egui::Rect::from_min_size(viewport.right_top() + egui::vec2(-size.x - 4.0, 4.0), size);
+                            let legend = ui.put(rect, egui::Button::new(text)).on_hover_text("Snap settings");
+                            egui::Popup::from_toggle_button_response(&legend)
+                                .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+                                .show(|ui| {
+                                    ui.label("Region edges snap to a grid of card pixels");
+                                    ui.add(egui::DragValue::new(&mut self.snap_step).range(1..=256).prefix("step ").suffix(" px"));
+                                    if ui.button("Turn snapping off").clicked() {
+                                        self.snap_step = 0;
+                                        ui.close();
+                                    }
                                 });
-                            });
+                            rect
+                        });
+
+                        // Pixel readout: card pixel under the cursor and its color in the atlas
+                        if let Some(cursor) = resp.hover_pos().filter(|p| img_rect.contains(*p)) {
+                            let scale_ui_to_px = 1.0 / scale;
+                            let local = cursor - img_rect.min;
+                            let dx = ((local.x * scale_ui_to_px).floor().max(0.0) as usize).min((cw as usize).max(1) - 1);
+                            let dy = ((local.y * scale_ui_to_px).floor().max(0.0) as usize).min((ch as usize).max(1) - 1);
+                            let (px, py) = self.unorient_px((dx, dy), [self.card_width, self.card_height]);
+                            let cols = self.cols().max(1);
+                            let [ox, oy] = self.card_origin(self.index % cols, self.index / cols);
+                            let rgba = self
+                                .atlas
+                                .as_ref()
+                                .and_then(|a| a.get_pixel_checked((ox + px) as u32, (oy + py) as u32))
+                                .map(|p| p.0);
+                            let text = match rgba {
+                                Some([r, g, b, a]) => format!("({px}, {py})  RGBA {r} {g} {b} {a}  #{r:02X}{g:02X}{b:02X}{a:02X}"),
+                                None => format!("({px}, {py})"),
+                            };
+                            let painter = ui.painter_at(viewport);
+                            let galley = painter.layout_no_wrap(text, egui::FontId::monospace(12.0), egui::Color32::WHITE);
+                            let pos = viewport.left_bottom() + egui::vec2(4.0, -4.0 - galley.size().y);
+                            painter.rect_filled(egui::Rect::from_min_size(pos, galley.size()).expand(3.0), 3.0, egui::Color32::from_black_alpha(180));
+                            if let Some([r, g, b, a]) = rgba {
+                                let swatch = egui::Rect::from_min_size(pos + egui::vec2(galley.size().x + 6.0, 0.0), egui::vec2(galley.size().y, galley.size().y));
+                                painter.rect_filled(swatch, 2.0, egui::Color32::from_rgba_unmultiplied(r, g, b, a));
+                            }
+                            painter.galley(pos, galley, egui::Color32::WHITE);
+                        }
+
+                        // Measure tool: the ruler follows the drag and stays until the next one starts
+                        if self.measure.active {
+                            let to_card_px = |p: egui::Pos2| {
+                                let local = ((p - img_rect.min) / scale).round();
+                                egui::pos2(local.x.clamp(0.0, cw), local.y.clamp(0.0, ch))
+                            };
+                            if !space_held && !on_minimap && !on_scrollbar && let Some(pos) = resp.interact_pointer_pos() {
+                                if resp.drag_started_by(egui::PointerButton::Primary) {
+                                    let origin = ctx.input(|i| i.pointer.press_origin()).unwrap_or(pos);
+                                    self.measure.ends = Some((to_card_px(origin), to_card_px(pos)));
+                                } else if resp.dragged_by(egui::PointerButton::Primary) && let Some((_, end)) = &mut self.measure.ends {
+                                    *end = to_card_px(pos);
+                                }
+                            }
+                            if let Some((a, b)) = self.measure.ends {
+                                let painter = ui.painter_at(viewport);
+                                let ends = [a, b].map(|p| img_rect.min + p.to_vec2() * scale);
+                                painter.line_segment(ends, egui::Stroke::new(2.0, egui::Color32::from_rgb(0, 220, 255)));
+                                for p in ends {
+                                    painter.circle_filled(p, 3.0, egui::Color32::from_rgb(0, 220, 255));
+                                }
+                                let d = b - a;
+                                let text = format!(
+                                    "({}, {}) → ({}, {})  dx {} dy {}  {:.1} px",
+                                    a.x, a.y, b.x, b.y, d.x, d.y, d.length()
+                                );
+                                let galley = painter.layout_no_wrap(text, egui::FontId::monospace(12.0), egui::Color32::WHITE);
+                                let pos = viewport.left_top() + egui::vec2(4.0, 4.0);
+                                painter.rect_filled(egui::Rect::from_min_size(pos, galley.size()).expand(3.0), 3.0, egui::Color32::from_black_alpha(180));
+                                painter.galley(pos, galley, egui::Color32::WHITE);
+                            }
                         }
 
+                        // Pointer state of the preview for the status bar's debug section (shown next frame)
+                        self.debug_readout = vec![
+                            format!("hovered: {}", resp.hovered()),
+                            format!("contains_pointer: {}", resp.contains_pointer()),
+                            format!("pointer_down_on: {}", resp.is_pointer_button_down_on()),
+                            format!("interact_pos: {:?}", resp.interact_pointer_pos()),
+                            format!("drag_started: {}", resp.drag_started_by(egui::PointerButton::Primary)),
+                            format!("dragged: {}", resp.dragged_by(egui::PointerButton::Primary)),
+                            format!("drag_stopped: {}", resp.drag_stopped_by(egui::PointerButton::Primary)),
+                            format!("clicked: {}", resp.clicked_by(egui::PointerButton::Primary)),
+                        ];
+
                         // Handle mouse input for region selection/creation
-                        if self.show_regions_panel
+                        if edit_regions
                         {
                             // Additional fallback: process raw pointer events to detect presses/drags/releases when Response misses them
-                            const DRAG_THRESHOLD: f32 = 4.0;
                             let events = ctx.input(|i| i.events.clone());
+                            // Touch screens also send pointer events for the primary finger, so only
+                            // the threshold differs; mouse input switches back to the normal one
+                            if events.iter().any(|e| matches!(e, egui::Event::Touch { .. })) {
+                                self.touch_input = true;
+                            } else if events.iter().any(|e| matches!(e, egui::Event::PointerButton { .. } | egui::Event::PointerMoved(_))) {
+                                self.touch_input = false;
+                            }
+                            let drag_threshold = if self.touch_input {
+                                self.drag_threshold.max(TOUCH_DRAG_THRESHOLD)
+                            } else {
+                                self.drag_threshold
+                            };
+                            // Region rectangle on screen, and a screen position as a card pixel (clamped to the card)
+                            let to_screen = |r: &Region| egui::Rect::from_min_size(
+                                img_rect.min + egui::vec2(r.x as f32, r.y as f32) * scale,
+                                egui::vec2(r.width as f32, r.height as f32) * scale,
+                            );
+                            let [card_w, card_h] = [self.card_width, self.card_height];
+                            let to_card_px = |p: egui::Pos2| {
+                                let local = (p - img_rect.min) / scale;
+                                (
+                                    (local.x.round().max(0.0) as usize).min(card_w),
+                                    (local.y.round().max(0.0) as usize).min(card_h),
+                                )
+                            };
                             for ev in events.iter() {
                                 match ev {
-                                    egui::Event::PointerButton { pos, button, pressed, .. } => {
+                                    egui::Event::PointerButton { pos, button, pressed, modifiers } => {
                                         if *button == egui::PointerButton::Primary {
-                                            if *pressed {
-                                                if img_rect.contains(*pos) {
+                                            let on_control = minimap.is_some_and(|m| m.contains(*pos))
+                                                || snap_legend.is_some_and(|l| l.contains(*pos))
+                                                || tracks.iter().flatten().any(|t| t.contains(*pos));
+                                            if *pressed && on_control { continue; }
+                                            // A press on a grip of the selected region starts resizing it, a press
+                                            // inside it moves it once dragged (or is a click); either way no new
+                                            // region is drawn
+                                            let edit = self
+                                                .selected_region
+                                                .and_then(|i| self.regions.get(i).map(|r| (i, r)))
+                                                .filter(|(_, r)| {
+                                                    *pressed && viewport.contains(*pos) && !space_held && !r.locked
+                                                        && ctx.layer_id_at(*pos) == Some(ui.layer_id())
+                                                })
+                                                .and_then(|(i, r)| {
+                                                    let rect = to_screen(r);
+                                                    RegionGrip::hit(rect, *pos)
+                                                        .map(RegionEditKind::Resize)
+                                                        .or_else(|| rect.contains(*pos).then_some(RegionEditKind::Move))
+                                                        .map(|kind| (i, kind, r))
+                                                });
+                                            if let Some((index, kind, r)) = edit {
+                                                self.region_edit = Some(RegionEdit {
+                                                    index,
+                                                    kind,
+                                                    orig: [r.x, r.y, r.width, r.height],
+                                                    press: to_card_px(*pos),
+                                                    press_pos: *pos,
+                                                    active: matches!(kind, RegionEditKind::Resize(_)),
+                                                    snapshot: self.regions.clone(),
+                                                });
+                                                self.pointer_down_on_image = false;
+                                            } else if !*pressed && let Some(edit) = self.region_edit.take() {
+                                                if edit.active {
+                                                    if edit.snapshot != self.regions {
+                                                        self.push_undo_snapshot(edit.snapshot);
+                                                    }
+                                                } else {
+                                                    // a click inside the selected region picks the next region under the pointer
+                                                    let local = (edit.press_pos - img_rect.min) / scale;
+                                                    self.click_region(local.x.floor().max(0.0) as usize, local.y.floor().max(0.0) as usize, modifiers.command);
+                                                }
+                                            } else if *pressed {
+                                                // Space + drag pans instead of drawing. Presses on a window or
+                                                // menu floating over the card belong to that, not to the card.
+                                                let uncovered = ctx.layer_id_at(*pos) == Some(ui.layer_id());
+                                                if viewport.contains(*pos) && img_rect.contains(*pos) && !space_held && uncovered {
                                                     self.pointer_down_on_image = true;
                                                     self.drag_start = Some(*pos);
                                                     self.drag_current = Some(*pos);
@@ -719,46 +5257,22 @@ impl eframe::App for TemplateApp {
                                                 // release
                                                 if self.pointer_down_on_image || self.dragging {
                                                     let end = *pos;
-                                                    if self.dragging {
+                                                    // a release away from the press is a drag even if no move event came in between
+                                                    let moved = self.drag_start.is_some_and(|start| (end - start).length() > drag_threshold);
+                                                    if self.dragging || moved {
                                                         if let Some(start) = self.drag_start {
-                                                            let local_start = start - img_rect.min;
-                                                            let local_end = end - img_rect.min;
-                                                            let sx = local_start.x.clamp(0.0, img_rect.width());
-                                                            let sy = local_start.y.clamp(0.0, img_rect.height());
-                                                            let ex = local_end.x.clamp(0.0, img_rect.width());
-                                                            let ey = local_end.y.clamp(0.0, img_rect.height());
-                                                            let lx = sx.min(ex);
-                                                            let ly = sy.min(ey);
-                                                            let lw = (sx - ex).abs();
-                                                            let lh = (sy - ey).abs();
-                                                            let scale_ui_to_px = 1.0 / scale;
-                                                            let px = (lx * scale_ui_to_px).round().max(0.0) as usize;
-                                                            let py = (ly * scale_ui_to_px).round().max(0.0) as usize;
-                                                            let pw = (lw * scale_ui_to_px).round().max(1.0) as usize;
-                                                            let ph = (lh * scale_ui_to_px).round().max(1.0) as usize;
-                                                            #[cfg(not(target_arch = "wasm32"))]
-                                                            {
-                                                                self.pending_region = Some([px, py, pw, ph]);
-                                                                self.new_region_name = format!("region{}", self.regions.len() + 1);
-                                                            }
+                                                            let [px, py, pw, ph] = drag_to_card_rect(img_rect, scale, start, end, [card_w, card_h]);
+                                                            self.pending_region = Some(snap_rect([px, py, pw, ph], self.snap_step, [card_w, card_h]));
+                                                            self.new_region_name = format!("region{}", self.regions.len() + 1);
                                                         }
                                                     } else {
                                                         // click
-                                                        if img_rect.contains(end) {
+                                                        if viewport.contains(end) && img_rect.contains(end) {
                                                             let local = end - img_rect.min;
                                                             let scale_ui_to_px = 1.0 / scale;
                                                             let px = (local.x * scale_ui_to_px).floor().max(0.0) as usize;
                                                             let py = (local.y * scale_ui_to_px).floor().max(0.0) as usize;
-                                                            let mut found: Option<usize> = None;
-                                                            for (i, r) in self.regions.iter().enumerate() {
-                                                                if px >= r.x && px < r.x + r.width && py >= r.y && py < r.y + r.height {
-                                                                    found = Some(i);
-                                                                    break;
-                                                                }
-                                                            }
-                                                            self.selected_region = found;
-                                                        } else {
-                                                            self.selected_region = None;
+                                                            self.click_region(px, py, modifiers.command);
                                                         }
                                                     }
                                                 }
@@ -767,39 +5281,47 @@ impl eframe::App for TemplateApp {
                                                 self.drag_current = None;
                                                 self.dragging = false;
                                             }
+                                        } else if *button == egui::PointerButton::Secondary && *pressed && viewport.contains(*pos) {
+                                            // Right-click picks the region for the context menu (and selects it)
+                                            let local = (*pos - img_rect.min) / scale;
+                                            self.context_region = img_rect
+                                                .contains(*pos)
+                                                .then(|| self.region_at(local.x.floor() as usize, local.y.floor() as usize))
+                                                .flatten();
+                                            if let Some(i) = self.context_region {
+                                                self.select_only(i);
+                                                self.inputs.context_rename = self.regions.get(i).map(|r| r.name.clone()).unwrap_or_default();
+                                            }
                                         }
                                     }
                                     egui::Event::PointerMoved(pos) => {
+                                        if let Some(edit) = &mut self.region_edit {
+                                            // a press inside the region becomes a move once it leaves the click threshold
+                                            if !edit.active && (*pos - edit.press_pos).length() <= drag_threshold { continue; }
+                                            edit.active = true;
+                                            let (px, py) = to_card_px(*pos);
+                                            let [x, y, w, h] = edit.apply(px, py, [card_w, card_h], self.snap_step);
+                                            if let Some(r) = self.regions.get_mut(edit.index) {
+                                                r.x = x;
+                                                r.y = y;
+                                                r.width = w;
+                                                r.height = h;
+                                            }
+                                            continue;
+                                        }
                                         if self.pointer_down_on_image {
                                             if let Some(start) = self.drag_start {
                                                 let dist = ((*pos) - start).length();
-                                                if !self.dragging && dist > DRAG_THRESHOLD {
+                                                if !self.dragging && dist > drag_threshold {
                                                     self.dragging = true;
                                                 }
                                                 if self.dragging {
                                                     self.drag_current = Some(*pos);
                                                     // update live pending region
-                                                    let local_start = start - img_rect.min;
-                                                    let local_pos = (*pos) - img_rect.min;
-                                                    let sx = local_start.x.clamp(0.0, img_rect.width());
-                                                    let sy = local_start.y.clamp(0.0, img_rect.height());
-                                                    let ex = local_pos.x.clamp(0.0, img_rect.width());
-                                                    let ey = local_pos.y.clamp(0.0, img_rect.height());
-                                                    let lx = sx.min(ex);
-                                                    let ly = sy.min(ey);
-                                                    let lw = (sx - ex).abs();
-                                                    let lh = (sy - ey).abs();
-                                                    let scale_ui_to_px = 1.0 / scale;
-                                                    let px = (lx * scale_ui_to_px).round().max(0.0) as usize;
-                                                    let py = (ly * scale_ui_to_px).round().max(0.0) as usize;
-                                                    let pw = (lw * scale_ui_to_px).round().max(1.0) as usize;
-                                                    let ph = (lh * scale_ui_to_px).round().max(1.0) as usize;
-                                                    #[cfg(not(target_arch = "wasm32"))]
-                                                    {
-                                                        self.pending_region = Some([px, py, pw, ph]);
-                                                        if self.new_region_name.is_empty() {
-                                                            self.new_region_name = format!("region{}", self.regions.len() + 1);
-                                                        }
+                                                    let [px, py, pw, ph] = drag_to_card_rect(img_rect, scale, start, *pos, [card_w, card_h]);
+                                                    self.pending_region = Some(snap_rect([px, py, pw, ph], self.snap_step, [card_w, card_h]));
+                                                    if self.new_region_name.is_empty() {
+                                                        self.new_region_name = format!("region{}", self.regions.len() + 1);
                                                     }
                                                 }
                                             }
@@ -810,189 +5332,61 @@ impl eframe::App for TemplateApp {
                             }
                         }
 
-                        /* old input handling disabled: */ if false {
-                        // Enhanced drag handling with a small movement threshold:
-                        // - Quick click (press+release without moving) is treated as selection
-                        // - Click+drag (movement > threshold) creates a pending region on release
-                        const DRAG_THRESHOLD: f32 = 4.0;
-
-
-                        // Prefer explicit PointerButton events to detect presses/releases reliably
-                        let events = ctx.input(|i| i.events.clone());
-                        let mut released_event = false;
-                        for ev in events.iter() {
-                            match ev {
-                                egui::Event::PointerButton { button, pressed, .. } => {
-                                    if *button == egui::PointerButton::Primary {
-                                        if !*pressed { released_event = true; }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-
-                        // Use hover_pos and interact_pos as before, but combine event-derived flags
-                        let hover_pos = ctx.input(|i| i.pointer.hover_pos());
-                        let pos_opt = ctx.input(|i| i.pointer.interact_pos()).or(hover_pos);
-                        let down = ctx.input(|i| i.pointer.any_down());
-                        let released = released_event || ctx.input(|i| i.pointer.any_released());
-
-                        // Start potential drag when pointer pressed while hovering the image
-                        // legacy press handling removed (using Response::drag_started_by)
-                        // if needed, use resp.drag_started_by/resp.clicked_by/resp.interact_pointer_pos() instead.
-
-                        // Update while pointer is down
-                        if down {
-                            if let (Some(start), Some(pos)) = (self.drag_start, pos_opt.or(hover_pos)) {
-                                self.drag_current = Some(pos);
-                                let dist = (pos - start).length();
-                                if dist > DRAG_THRESHOLD {
-                                    self.dragging = true;
-                                }
-
-                                // While dragging, update a live pending region so user can Add even if release isn't observed
-                                if self.dragging {
-                                    // Convert screen coords to local image coords
-                                    let local_start = start - img_rect.min;
-                                    let local_pos = pos - img_rect.min;
-
-                                    // Clamp to image rect
-                                    let sx = local_start.x.clamp(0.0, img_rect.width());
-                                    let sy = local_start.y.clamp(0.0, img_rect.height());
-                                    let ex = local_pos.x.clamp(0.0, img_rect.width());
-                                    let ey = local_pos.y.clamp(0.0, img_rect.height());
-
-                                    let lx = sx.min(ex);
-                                    let ly = sy.min(ey);
-                                    let lw = (sx - ex).abs();
-                                    let lh = (sy - ey).abs();
-
-                                    // Convert to card pixel coords
-                                    let scale_ui_to_px = 1.0 / scale;
-                                    let px = (lx * scale_ui_to_px).round().max(0.0) as usize;
-                                    let py = (ly * scale_ui_to_px).round().max(0.0) as usize;
-                                    let pw = (lw * scale_ui_to_px).round().max(1.0) as usize;
-                                    let ph = (lh * scale_ui_to_px).round().max(1.0) as usize;
-
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    {
-                                        self.pending_region = Some([px, py, pw, ph]);
-                                        if self.new_region_name.is_empty() {
-                                            self.new_region_name = format!("region{}", self.regions.len() + 1);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-
-                        // On release: if we were dragging, create a pending region; otherwise selection logic handles clicks
-                        if released && self.drag_start.is_some() {
-                            let start = self.drag_start.unwrap();
-                            let end = pos_opt.or(self.drag_current).or(hover_pos).unwrap_or(start);
-
-                            if self.dragging {
-                                // Convert screen coords to local image coords
-                                let local_start = start - img_rect.min;
-                                let local_end = end - img_rect.min;
-
-                                // Clamp to image rect
-                                let sx = local_start.x.clamp(0.0, img_rect.width());
-                                let sy = local_start.y.clamp(0.0, img_rect.height());
-                                let ex = local_end.x.clamp(0.0, img_rect.width());
-                                let ey = local_end.y.clamp(0.0, img_rect.height());
-
-                                let lx = sx.min(ex);
-                                let ly = sy.min(ey);
-                                let lw = (sx - ex).abs();
-                                let lh = (sy - ey).abs();
-
-                                // Convert to card pixel coords
-                                let scale_ui_to_px = 1.0 / scale; // since desired_size = card_size * scale
-                                let px = (lx * scale_ui_to_px).round().max(0.0) as usize;
-                                let py = (ly * scale_ui_to_px).round().max(0.0) as usize;
-                                let pw = (lw * scale_ui_to_px).round().max(1.0) as usize;
-                                let ph = (lh * scale_ui_to_px).round().max(1.0) as usize;
-
-                                #[cfg(not(target_arch = "wasm32"))]
-                                {
-                                    self.pending_region = Some([px, py, pw, ph]);
-                                    self.new_region_name = format!("region{}", self.regions.len() + 1);
-                                }
-                            }
-
-                            self.drag_start = None;
-                            self.drag_current = None;
-                            self.dragging = false;
-                        }
-
-                        // Also handle pointer-up that occurred outside of widget (e.g. released while cursor moved off image)
-                        let current_down = down;
-                        if self.dragging && self.last_pointer_down && !current_down {
-                            // Treat similar to release while dragging
-                            if let Some(start) = self.drag_start {
-                                let end = self.drag_current.or(pos_opt).or(ctx.input(|i| i.pointer.hover_pos())).unwrap_or(start);
-
-                                // Convert screen coords to local image coords
-                                let local_start = start - img_rect.min;
-                                let local_end = end - img_rect.min;
-
-                                // Clamp to image rect
-                                let sx = local_start.x.clamp(0.0, img_rect.width());
-                                let sy = local_start.y.clamp(0.0, img_rect.height());
-                                let ex = local_end.x.clamp(0.0, img_rect.width());
-                                let ey = local_end.y.clamp(0.0, img_rect.height());
-
-                                let lx = sx.min(ex);
-                                let ly = sy.min(ey);
-                                let lw = (sx - ex).abs();
-                                let lh = (sy - ey).abs();
-
-                                // Convert to card pixel coords
-                                let scale_ui_to_px = 1.0 / scale; // since desired_size = card_size * scale
-                                let px = (lx * scale_ui_to_px).round().max(0.0) as usize;
-                                let py = (ly * scale_ui_to_px).round().max(0.0) as usize;
-                                let pw = (lw * scale_ui_to_px).round().max(1.0) as usize;
-                                let ph = (lh * scale_ui_to_px).round().max(1.0) as usize;
-
-                                #[cfg(not(target_arch = "wasm32"))]
-                                {
-                                    self.pending_region = Some([px, py, pw, ph]);
-                                    self.new_region_name = format!("region{}", self.regions.len() + 1);
-                                }
+                        // A long press is the touch counterpart of a right-click: it selects the
+                        // region under the finger and opens its context menu
+                        if edit_regions && resp.long_touched() && let Some(pos) = resp.interact_pointer_pos() {
+                            let local = (pos - img_rect.min) / scale;
+                            self.context_region = img_rect
+                                .contains(pos)
+                                .then(|| self.region_at(local.x.floor() as usize, local.y.floor() as usize))
+                                .flatten();
+                            if let Some(i) = self.context_region {
+                                self.select_only(i);
+                                self.inputs.context_rename = self.regions.get(i).map(|r| r.name.clone()).unwrap_or_default();
                             }
-
-                            self.drag_start = None;
-                            self.drag_current = None;
-                            self.dragging = false;
                         }
 
-                        // Update last pointer down state for next frame
-                        self.last_pointer_down = current_down;
-
-                        // Click (release while hovering) selects a region if released inside it; clicking outside clears selection.
-                        // Do not run selection if a pending region was just created this frame.
-                        // legacy click handling removed; use resp.clicked_by to detect clicks instead
-
+                        if edit_regions {
+                            self.region_context_menu(&resp);
                         }
 
                         // Paint overlays (existing regions and drag preview)
-                        if self.show_regions_panel {
-                            let painter = ui.painter();
+                        if edit_regions {
+                            let painter = ui.painter_at(viewport);
+                            // The highlighted pair only stays highlighted while it still overlaps
+                            let overlap = self.highlighted_overlap.filter(|&(a, b)| {
+                                matches!((self.regions.get(a), self.regions.get(b)), (Some(ra), Some(rb)) if ra.overlaps(rb))
+                            });
                             // Draw existing regions
-                            for (i, r) in self.regions.iter().enumerate() {
+                            for (i, r) in self.regions.iter().enumerate().filter(|(_, r)| r.visible) {
                                 let x = img_rect.min.x + (r.x as f32) * scale;
                                 let y = img_rect.min.y + (r.y as f32) * scale;
                                 let w = (r.width as f32) * scale;
                                 let h = (r.height as f32) * scale;
                                 let rect = egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(w, h));
-                                let color = if self.selected_region == Some(i) { egui::Color32::LIGHT_BLUE } else { egui::Color32::from_rgba_unmultiplied(200, 100, 100, 180) };
-                                let stroke = egui::Stroke::new(2.0, color);
+                                let color = if overlap.is_some_and(|(a, b)| i == a || i == b) {
+                                    egui::Color32::from_rgb(255, 0, 255)
+                                } else if r.out_of_bounds([self.card_width, self.card_height]) {
+                                    egui::Color32::RED
+                                } else if self.selected_regions.contains(&i) {
+                                    egui::Color32::LIGHT_BLUE
+                                } else {
+                                    let [cr, cg, cb, ca] = r.color.unwrap_or(DEFAULT_REGION_COLOR);
+                                    egui::Color32::from_rgba_unmultiplied(cr, cg, cb, ca)
+                                };
+                                let stroke = egui::Stroke::new(self.overlay_stroke_width, color);
                                 painter.line_segment([rect.left_top(), rect.right_top()], stroke);
                                 painter.line_segment([rect.right_top(), rect.right_bottom()], stroke);
                                 painter.line_segment([rect.right_bottom(), rect.left_bottom()], stroke);
                                 painter.line_segment([rect.left_bottom(), rect.left_top()], stroke);
                                 if self.selected_region == Some(i) {
-                                    painter.rect_filled(rect.expand(2.0), 2.0, egui::Color32::from_rgba_unmultiplied(40, 100, 160, 48));
+                                    painter.rect_filled(rect.expand(2.0), 2.0, egui::Color32::from_rgba_unmultiplied(40, 100, 160, self.overlay_fill_alpha));
+                                    // Resize grips
+                                    for grip in RegionGrip::ALL {
+                                        let g = egui::Rect::from_center_size(grip.anchor(rect), egui::Vec2::splat(GRIP_SIZE));
+                                        painter.rect_filled(g, 1.0, egui::Color32::WHITE);
+                                        painter.rect_stroke(g, 1.0, egui::Stroke::new(1.0, egui::Color32::DARK_BLUE), egui::StrokeKind::Inside);
+                                    }
                                 }
                             }
 
@@ -1005,11 +5399,25 @@ impl eframe::App for TemplateApp {
                                 let lw = (local_start.x - local_cur.x).abs().clamp(1.0, img_rect.width());
                                 let lh = (local_start.y - local_cur.y).abs().clamp(1.0, img_rect.height());
                                 let rect = egui::Rect::from_min_size(img_rect.min + egui::vec2(lx, ly), egui::vec2(lw, lh));
-                                let stroke = egui::Stroke::new(2.0, egui::Color32::YELLOW);
+                                let stroke = egui::Stroke::new(self.overlay_stroke_width, egui::Color32::YELLOW);
                                 painter.line_segment([rect.left_top(), rect.right_top()], stroke);
                                 painter.line_segment([rect.right_top(), rect.right_bottom()], stroke);
                                 painter.line_segment([rect.right_bottom(), rect.left_bottom()], stroke);
                                 painter.line_segment([rect.left_bottom(), rect.left_top()], stroke);
+
+                                if self.dragging {
+                                    // Crosshair through the pointer to line the edge up with card features
+                                    let guide = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(255, 255, 0, 120));
+                                    painter.hline(viewport.x_range(), cur.y, guide);
+                                    painter.vline(cur.x, viewport.y_range(), guide);
+                                    // Live size/position: the exact rect that will be added on release
+                                    let card = [self.card_width, self.card_height];
+                                    let [px, py, pw, ph] = snap_rect(drag_to_card_rect(img_rect, scale, start, cur, card), self.snap_step, card);
+                                    let galley = painter.layout_no_wrap(format!("{pw}×{ph} @ {px},{py}"), egui::FontId::monospace(12.0), egui::Color32::WHITE);
+                                    let pos = cur + egui::vec2(12.0, 12.0);
+                                    painter.rect_filled(egui::Rect::from_min_size(pos, galley.size()).expand(3.0), 3.0, egui::Color32::from_black_alpha(180));
+                                    painter.galley(pos, galley, egui::Color32::WHITE);
+                                }
                             }
 
                             // Draw pending region (after release, before naming)
@@ -1019,37 +5427,626 @@ impl eframe::App for TemplateApp {
                                 let w = (pw as f32) * scale;
                                 let h = (ph as f32) * scale;
                                 let rect = egui::Rect::from_min_size(egui::pos2(x, y), egui::vec2(w, h));
-                                let stroke = egui::Stroke::new(2.0, egui::Color32::from_rgba_unmultiplied(255, 200, 0, 200));
+                                let stroke = egui::Stroke::new(self.overlay_stroke_width, egui::Color32::from_rgba_unmultiplied(255, 200, 0, 200));
                                 painter.line_segment([rect.left_top(), rect.right_top()], stroke);
                                 painter.line_segment([rect.right_top(), rect.right_bottom()], stroke);
                                 painter.line_segment([rect.right_bottom(), rect.left_bottom()], stroke);
                                 painter.line_segment([rect.left_bottom(), rect.left_top()], stroke);
-                                painter.rect_filled(rect, 0.0, egui::Color32::from_rgba_unmultiplied(255, 200, 0, 40));
+                                painter.rect_filled(rect, 0.0, egui::Color32::from_rgba_unmultiplied(255, 200, 0, self.overlay_fill_alpha));
                             }
                         }
 
+                        for axis in 0..2 {
+                            let Some((track, thumb, _, _)) = scrollbar(axis, self.pan_offset) else { continue };
+                            let painter = ui.painter_at(viewport);
+                            painter.rect_filled(track, 0.0, egui::Color32::from_black_alpha(100));
+                            let hovered = on_scrollbar || ctx.input(|i| i.pointer.hover_pos()).is_some_and(|p| track.contains(p));
+                            painter.rect_filled(thumb.shrink(1.0), 3.0, egui::Color32::from_gray(if hovered { 220 } else { 160 }));
+                        }
+
+                        if let Some(mini) = minimap {
+                            let painter = ui.painter_at(viewport);
+                            painter.rect_filled(mini.expand(2.0), 2.0, egui::Color32::from_black_alpha(160));
+                            painter.image(tex.id(), mini, uv, egui::Color32::WHITE);
+                            // The visible part of the card, mapped into the mini-map
+                            let visible = viewport.intersect(img_rect);
+                            let to_mini = |p: egui::Pos2| mini.min + (p - img_rect.min) / img_rect.size() * mini.size();
+                            let frame = egui::Rect::from_min_max(to_mini(visible.min), to_mini(visible.max));
+                            painter.rect_stroke(frame, 0.0, egui::Stroke::new(1.5, egui::Color32::WHITE), egui::StrokeKind::Inside);
+                        }
+
+                        // Loupe: a magnified box next to the cursor, drawn from a sub-rect of the card texture
+                        if self.show_loupe
+                            && let Some(cursor) = resp.hover_pos().filter(|p| img_rect.contains(*p) && !minimap.is_some_and(|m| m.contains(*p)))
+                        {
+                            let center = ((cursor - img_rect.min) / scale).to_pos2();
+                            let (src, dest) = loupe_source(center, card_size);
+                            // below right of the cursor, flipped to the other side near the viewport edges
+                            let mut min = cursor + egui::vec2(24.0, 24.0);
+                            if min.x + LOUPE_SIDE > viewport.max.x { min.x = cursor.x - 24.0 - LOUPE_SIDE; }
+                            if min.y + LOUPE_SIDE > viewport.max.y { min.y = cursor.y - 24.0 - LOUPE_SIDE; }
+                            let frame = egui::Rect::from_min_size(min, egui::Vec2::splat(LOUPE_SIDE));
+                            let painter = ui.painter_at(viewport);
+                            painter.rect_filled(frame, 0.0, egui::Color32::from_gray(20));
+                            let uv = egui::Rect::from_min_max((src.min.to_vec2() / card_size).to_pos2(), (src.max.to_vec2() / card_size).to_pos2());
+                            painter.image(tex.id(), dest.translate(min.to_vec2()), uv, egui::Color32::WHITE);
+                            // outline the pixel under the cursor
+                            let pixel = egui::Rect::from_min_size(
+                                frame.center() - (center - center.floor()) * LOUPE_ZOOM,
+                                egui::Vec2::splat(LOUPE_ZOOM),
+                            );
+                            painter.rect_stroke(pixel, 0.0, egui::Stroke::new(1.0, egui::Color32::YELLOW), egui::StrokeKind::Outside);
+                            painter.rect_stroke(frame, 0.0, egui::Stroke::new(1.5, egui::Color32::WHITE), egui::StrokeKind::Outside);
+                        }
                     });
 
 
                 } else {
-                    ui.label("No preview available for this index (out of range or atlas missing).");
+                    let reason = self.no_preview_reason();
+                    ui.colored_label(if self.atlas.is_some() { egui::Color32::YELLOW } else { ui.visuals().text_color() }, reason);
                 }
             }
         });
 
+        self.handle_dropped_files(ctx);
+
         // On web builds, check if the user picked a file (async callback writes bytes into the picker buffer)
         #[cfg(target_arch = "wasm32")]
         {
             if let Some((bytes, filename)) = crate::file_picker::take_selected_image_bytes() {
-                match self.load_atlas_bytes(&bytes) {
-                    Ok(()) => {
-                        self.error = None;
-                        self.atlas_path = Some(filename);
-                    }
+                match self.load_atlas_bytes(&bytes, &filename) {
+                    Ok(()) => self.error = None,
                     Err(e) => self.error = Some(e),
                 }
             }
+            if let Some(text) = crate::file_picker::take_selected_text() {
+                self.apply_web_import(&text);
+            }
+            // A sample atlas that could not be fetched (missing from the deployment, offline, ...)
+            if let Some(e) = crate::file_picker::take_asset_error() {
+                self.error = Some(e);
+            }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn texturepacker_frames_map_into_the_card_cell() {
+        let json = r#"{"frames": {
+            "inside.png": {"frame": {"x": 110, "y": 220, "w": 30, "h": 40}, "rotated": false},
+            "turned.png": {"frame": {"x": 100, "y": 200, "w": 10, "h": 20}, "rotated": true},
+            "outside.png": {"frame": {"x": 190, "y": 200, "w": 20, "h": 20}},
+            "huge.png": {"frame": {"x": 110, "y": 220, "w": 18446744073709551615, "h": 1}}
+        }, "meta": {"app": "https://www.codeandweb.com/texturepacker"}}"#;
+        let (regions, skipped) = regions_from_texturepacker(json, [100, 200, 100, 100]).expect("valid sheet");
+        assert_eq!(skipped, 2, "frames crossing the cell edge or past usize are skipped");
+        let rects: Vec<_> = regions.iter().map(|r| (r.name.as_str(), [r.x, r.y, r.width, r.height])).collect();
+        assert_eq!(rects, [("inside", [10, 20, 30, 40]), ("turned", [0, 0, 20, 10])], "frames are made cell-relative");
+    }
+
+    #[test]
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn regions_to_rust_escapes_names() {
+        let app = TemplateApp {
+            regions: vec![Region::new(r#"say "hi" \ bye"#.to_owned(), [1, 2, 3, 4])],
+            ..Default::default()
+        };
+        let code = app.regions_to_rust();
+        assert!(code.contains("pub const REGIONS: &[(&str, usize, usize, usize, usize)] = &["), "missing declaration: {code}");
+        assert!(code.contains(r#"    ("say \"hi\" \\ bye", 1, 2, 3, 4),"#), "name not escaped: {code}");
+        assert!(code.trim_end().ends_with("];"), "table not closed: {code}");
+    }
+
+    #[test]
+    fn regions_to_svg_labels_rects_and_embeds_the_card() {
+        assert_eq!(base64(b"Man"), "TWFu", "whole group");
+        assert_eq!(base64(b"Ma"), "TWE=", "one byte short");
+        assert_eq!(base64(b"M"), "TQ==", "two bytes short");
+
+        let mut app = TemplateApp { atlas: Some(std::sync::Arc::new(image::RgbaImage::new(4, 3))), atlas_size: [4, 3], card_width: 4, card_height: 3, ..Default::default() };
+        app.regions = vec![Region::new("a<b".to_owned(), [1, 0, 2, 3])];
+        let svg = app.regions_to_svg(false);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"4\" height=\"3\""), "sized to the card: {svg}");
+        assert!(svg.contains("<rect x=\"1\" y=\"0\" width=\"2\" height=\"3\""), "rect matches the region: {svg}");
+        assert!(svg.contains(">a&lt;b</text>"), "name is escaped: {svg}");
+        assert!(!svg.contains("<image"), "no background unless asked: {svg}");
+        assert!(app.regions_to_svg(true).contains("href=\"data:image/png;base64,iVBORw0KGgo"), "card embedded as PNG");
+    }
+
+    #[test]
+    fn switching_card_sets_swaps_size_and_regions() {
+        let mut app = TemplateApp { atlas_size: [1000, 1000], card_width: 100, card_height: 200, ..Default::default() };
+        app.regions = vec![Region::new("title".to_owned(), [0, 0, 10, 10])];
+        app.add_card_set();
+        app.index = 3;
+        app.add_card_set();
+        app.set_card_size(50, 50, "test");
+        app.regions.clear();
+
+        app.select_card_set(0);
+        assert_eq!([app.card_width, app.card_height, app.index], [100, 200, 0], "first set not restored");
+        assert_eq!(app.regions.len(), 1, "first set regions not restored");
+        app.select_card_set(1);
+        assert_eq!([app.card_width, app.card_height, app.index], [50, 50, 3], "edits to the second set were lost");
+        assert!(app.regions.is_empty(), "second set regions were not kept");
+    }
+
+    #[test]
+    fn switching_atlas_keeps_the_active_card_set() {
+        let mut app = TemplateApp { atlas_path: Some("a.png".to_owned()), card_width: 100, card_height: 200, ..Default::default() };
+        app.add_card_set();
+        app.regions = vec![Region::new("title".to_owned(), [0, 0, 10, 10])];
+        app.regions_by_atlas.insert("b.png".to_owned(), vec![Region::new("other".to_owned(), [0, 0, 5, 5])]);
+        app.switch_atlas("b.png".to_owned());
+        assert_eq!(app.card_sets[0].regions.len(), 1, "active set lost its regions");
+        assert_eq!(app.card_sets[0].regions[0].name, "title", "active set took the new atlas's regions");
+    }
+
+    #[test]
+    fn persisted_selection_is_validated() {
+        let mut app: TemplateApp = serde_json::from_str(
+            r#"{"regions":[{"name":"a","x":0,"y":0,"width":1,"height":1}],"selected_region":3,"selected_regions":[0,3]}"#,
+        )
+        .expect("state should deserialize");
+        app.validate_selection();
+        assert_eq!(app.selected_region, Some(0), "out-of-range primary selection not replaced");
+        assert_eq!(app.selected_regions.len(), 1, "out-of-range selection not dropped");
+    }
+
+    #[test]
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn atlas_space_regions_map_onto_the_card() {
+        let regions = vec![
+            Region::new("in".to_owned(), [120, 230, 10, 10]),
+            Region::new("off".to_owned(), [95, 230, 10, 10]),
+            Region::new("huge".to_owned(), [120, 230, usize::MAX, 10]),
+        ];
+        let (card, skipped) = regions_to_card_space(regions, [100, 200, 50, 50]);
+        assert_eq!(skipped, 2, "regions crossing the cell edge or past usize should be dropped");
+        assert_eq!(card.first().map(|r| [r.x, r.y]), Some([20, 30]), "region not made card-relative");
+    }
+
+    #[test]
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn regions_file_formats_are_told_apart() {
+        let legacy = r#"[{"name":"a","x":0,"y":0,"width":1,"height":1}]"#;
+        let older = r#"{"image_size":[10,10],"regions":[]}"#;
+        let atlas = r#"{"image_size":[10,10],"coordinate_space":"atlas","regions":[]}"#;
+        assert_eq!(regions_file_format(legacy), Some(regions_file_format_name(true, CoordinateSpace::Card)), "legacy list");
+        assert_eq!(regions_file_format(older), Some(regions_file_format_name(false, CoordinateSpace::Card)), "missing space means card");
+        assert_eq!(regions_file_format(atlas), Some(regions_file_format_name(false, CoordinateSpace::Atlas)), "atlas space");
+        assert_eq!(regions_file_format(r#"{"frames":{}}"#), None, "not a regions file");
+        assert_eq!(regions_file_format("[]"), Some(regions_file_format_name(true, CoordinateSpace::Card)), "empty legacy list");
+
+        let mut app = TemplateApp { regions: vec![Region::new("a".to_owned(), [0, 0, 1, 1])], ..Default::default() };
+        app.apply_regions_file("[]");
+        assert!(app.error.is_none(), "an empty legacy list should load");
+        assert!(app.regions.is_empty(), "an empty legacy list leaves no regions");
+        app.apply_regions_file(r#"{"version":99,"image_size":[10,10],"regions":[]}"#);
+        assert!(app.error.as_deref().is_some_and(|e| e.contains("newer")), "a newer version is reported as such");
+    }
+
+    #[test]
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn atlas_manifest_round_trips() {
+        let app = TemplateApp {
+            atlas_path: Some("assets/light_cards.png".to_owned()),
+            atlas_size: [210, 100],
+            card_width: 100,
+            card_height: 100,
+            spacing_x: 10,
+            regions: vec![Region::new("title".to_owned(), [1, 2, 3, 4])],
+            ..Default::default()
+        };
+        for shared in [false, true] {
+            let manifest = app.atlas_manifest(shared);
+            assert_eq!(manifest.atlas, "light_cards.png", "atlas should be the file name");
+            assert_eq!(manifest.cards.iter().map(|c| [c.x, c.y]).collect::<Vec<_>>(), [[0, 0], [110, 0]], "card origins");
+            assert_eq!(manifest.regions.is_some(), shared, "shared list present only when shared");
+            assert!(manifest.cards.iter().all(|c| c.regions.is_some() != shared), "per-card lists only when not shared");
+            let json = serde_json::to_string(&manifest).expect("manifest serializes");
+            let back: AtlasManifest = serde_json::from_str(&json).expect("manifest deserializes");
+            assert_eq!(back, manifest, "manifest should round-trip");
+        }
+    }
+
+    #[test]
+    fn selection_verbs_keep_a_valid_primary() {
+        let region = |x, y| Region::new(String::new(), [x, y, 10, 10]);
+        let mut app = TemplateApp { regions: vec![region(0, 0), region(10, 0), region(5, 5), region(40, 40)], ..Default::default() };
+        app.select_only(1);
+        app.invert_selection();
+        assert_eq!(app.selected_regions.iter().copied().collect::<Vec<_>>(), [0, 2, 3], "inverted selection");
+        assert_eq!(app.selected_region, Some(0), "primary should move into the new selection");
+        app.select_overlapping();
+        assert_eq!(app.selected_regions.iter().copied().collect::<Vec<_>>(), [0, 1, 2], "overlapping regions");
+        assert_eq!(app.selected_region, Some(0), "primary kept when still selected");
+        app.select_all_regions();
+        assert_eq!(app.selected_regions.len(), 4, "every region selected");
+    }
+
+    #[test]
+    fn unique_region_name_trims_and_suffixes() {
+        let app = TemplateApp {
+            regions: vec![Region::new("title".to_owned(), [0, 0, 1, 1]), Region::new("title_2".to_owned(), [0, 0, 1, 1])],
+            ..Default::default()
+        };
+        assert_eq!(app.unique_region_name("  art "), "art", "free names are only trimmed");
+        assert_eq!(app.unique_region_name("title"), "title_3", "taken suffixes are skipped");
+        assert_eq!(app.unique_region_name("   "), "region3", "empty names fall back to a numbered name");
+    }
+
+    #[test]
+    fn unorient_px_inverts_transform_card() {
+        let (w, h) = (3u32, 2u32);
+        let card = image::RgbaImage::from_fn(w, h, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let mut app = TemplateApp::default();
+        for rotation in [0, 90, 180, 270] {
+            for (flip_h, flip_v) in [(false, false), (true, false), (false, true), (true, true)] {
+                (app.card_rotation, app.flip_h, app.flip_v) = (rotation, flip_h, flip_v);
+                let shown = transform_card(card.clone(), rotation, flip_h, flip_v);
+                for (dx, dy, p) in shown.enumerate_pixels() {
+                    let (x, y) = app.unorient_px((dx as usize, dy as usize), [w as usize, h as usize]);
+                    assert_eq!([x as u8, y as u8], [p.0[0], p.0[1]], "rotation {rotation}, flips {flip_h}/{flip_v}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fractional_pitch_does_not_drift() {
+        let app = TemplateApp {
+            atlas_size: [5347, 752],
+            card_width: 535,
+            card_height: 752,
+            card_pitch: Some([534.7, 752.0]),
+            ..Default::default()
+        };
+        assert_eq!(app.cols(), 10, "last column lost to rounding");
+        assert_eq!(app.card_origin(9, 0), [4812, 0], "column 9 not at round(9 * 534.7)");
+        assert_eq!(app.leftover_pixels(), [0, 0]);
+
+        // A pitch that no longer matches the card size (e.g. after picking a preset) is ignored
+        let app = TemplateApp { card_width: 500, ..app };
+        assert_eq!(app.card_origin(9, 0), [4500, 0]);
+    }
+
+    #[test]
+    fn region_tags_parse_and_filter() {
+        let mut r = Region::new("Title".to_owned(), [0, 0, 1, 1]);
+        r.tags = parse_tags(" text, Icon,, text ,");
+        assert_eq!(r.tags, ["text", "Icon"], "blank or repeated tags kept");
+        assert!(r.matches_filter("icon") && r.matches_filter("tit") && !r.matches_filter("art"));
+
+        // old files without tags still load, and untagged regions are saved without the field
+        let old: Region = serde_json::from_str(r#"{"name":"a","x":0,"y":0,"width":1,"height":1}"#).expect("old region");
+        assert!(old.tags.is_empty());
+        assert!(!serde_json::to_string(&old).expect("json").contains("tags"));
+    }
+
+    #[test]
+    fn oversized_card_is_explained() {
+        let app = TemplateApp {
+            atlas: Some(std::sync::Arc::new(image::RgbaImage::new(535, 752))),
+            atlas_size: [535, 752],
+            card_width: 800,
+            card_height: 900,
+            ..Default::default()
+        };
+        assert_eq!(app.no_preview_reason(), "Card size (800×900) is larger than the atlas (535×752). Reduce the card width/height.");
+    }
+
+    #[test]
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn settings_round_trip_keeps_local_window() {
+        let exported = TemplateApp {
+            user_presets: vec![("Mini".to_owned(), 41, 63)],
+            snap_step: 4,
+            window_size: [1.0, 2.0],
+            ..Default::default()
+        };
+        let json = exported.settings_json().expect("export");
+        let here = TemplateApp { ownership_confirmed: true, window_size: [800.0, 600.0], ..Default::default() };
+        let imported = here.settings_from_json(&json).expect("import");
+        assert_eq!(imported.user_presets, exported.user_presets);
+        assert_eq!(imported.snap_step, 4);
+        assert!(imported.ownership_confirmed, "ownership confirmation lost");
+        assert_eq!(imported.window_size, [800.0, 600.0], "window size taken from the other machine");
+        assert!(here.settings_from_json("{not json").is_err());
+    }
+
+    #[test]
+    #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
+    fn imported_settings_are_range_checked() {
+        let here = TemplateApp::default();
+        let imported = here
+            .settings_from_json(r#"{"card_width":0,"margin_y":99999,"offset_y":99999,"user_presets":[["",1,1]]}"#)
+            .expect("import");
+        assert_eq!([imported.card_width, imported.margin_y], [1, MAX_CARD_SIDE], "layout not clamped");
+        assert_eq!(imported.offset_y, MAX_CARD_SIDE as i32, "offset not clamped");
+        assert!(imported.user_presets.is_empty(), "nameless preset kept");
+        assert!(imported.warning.is_some(), "adjusting the import should leave a warning");
+    }
+
+    #[test]
+    fn navigation_skips_empty_cards() {
+        // four 2×2 cards in a row; only the first and the last have an opaque pixel
+        let mut atlas = image::RgbaImage::new(8, 2);
+        atlas.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+        atlas.put_pixel(7, 1, image::Rgba([0, 0, 0, 1]));
+        let mut app = TemplateApp { atlas: Some(std::sync::Arc::new(atlas)), atlas_size: [8, 2], card_width: 2, card_height: 2, ..Default::default() };
+        assert!(!app.is_card_empty(0) && app.is_card_empty(1) && app.is_card_empty(2) && !app.is_card_empty(3));
+        assert_eq!(app.step_index(1), 1, "stepped over an empty card without the option");
+        app.skip_empty_cards = true;
+        assert_eq!(app.step_index(1), 3);
+        assert_eq!(app.step_index(-1), 0, "moved off the first card");
+        let atlas = app.atlas.as_ref().expect("atlas set above");
+        assert_eq!(AtlasGrid::new(atlas, app.grid_layout()).empty_cards(), 2, "the middle cards are empty");
+    }
+
+    #[test]
+    fn card_size_is_remembered_per_atlas() {
+        let mut app = TemplateApp { atlas_path: Some("a.png".to_owned()), card_width: 100, card_height: 200, ..Default::default() };
+        app.switch_atlas("b.png".to_owned());
+        assert_eq!([app.card_width, app.card_height], [100, 200], "unknown atlas should keep the current size");
+        app.card_width = 50;
+        app.card_height = 50;
+        app.switch_atlas("a.png".to_owned());
+        assert_eq!([app.card_width, app.card_height], [100, 200]);
+        app.switch_atlas("b.png".to_owned());
+        assert_eq!([app.card_width, app.card_height], [50, 50]);
+    }
+
+    #[test]
+    fn selection_is_remembered_per_atlas() {
+        let region = |x, y| Region::new(String::new(), [x, y, 10, 10]);
+        let mut app = TemplateApp { atlas_path: Some("a.png".to_owned()), regions: vec![region(0, 0), region(10, 0)], ..Default::default() };
+        app.select_only(1);
+        app.regions_by_atlas.insert("b.png".to_owned(), vec![region(0, 0)]);
+        app.selection_by_atlas.insert("b.png".to_owned(), (Some(4), [0, 4].into()));
+        app.switch_atlas("b.png".to_owned());
+        assert_eq!(app.selected_region, Some(0), "stale primary selection of the other atlas not replaced");
+        assert_eq!(app.selected_regions.len(), 1, "stale selection of the other atlas not dropped");
+        app.switch_atlas("a.png".to_owned());
+        assert_eq!(app.selected_region, Some(1), "selection of the first atlas not restored");
+        app.stash_working_regions();
+        let json = serde_json::to_value(&app).expect("state should serialize");
+        assert!(json.get("selected_region").is_none(), "the working selection is saved per atlas");
+        assert_eq!(app.selection_by_atlas.get("a.png").and_then(|s| s.0), Some(1), "selection not stashed");
+    }
+
+    #[test]
+    fn sample_atlas_size_does_not_overwrite_the_previous_atlas() {
+        let mut app = TemplateApp { atlas_path: Some("a.png".to_owned()), card_width: 100, card_height: 200, ..Default::default() };
+        app.atlas_jobs.pending_preset_size = Some(("assets/paths1.png".to_owned(), [1380, 912]));
+        app.install_atlas(image::RgbaImage::new(2760, 912), "assets/paths1.png".to_owned(), None);
+        assert_eq!([app.card_width, app.card_height], [1380, 912], "sample atlas size not applied");
+        assert_eq!(app.card_size_by_atlas.get("a.png"), Some(&[100, 200]), "previous atlas took the sample's size");
+    }
+
+    #[test]
+    fn drag_maps_to_card_pixels_at_any_pixels_per_point() {
+        // A feature at card pixels [20, 30, 15, 10], shown at 3 device pixels per card pixel. Pointer
+        // positions come in points, landing up to 0.4 device pixels off the feature's edges.
+        for ppp in [1.0, 1.25, 1.5, 2.0] {
+            let scale = 3.0 / ppp;
+            let img_rect = egui::Rect::from_min_size(egui::pos2(10.3, 47.9), egui::vec2(100.0, 100.0) * scale);
+            let at = |x: f32, y: f32, jitter: f32| img_rect.min + egui::vec2(x * scale + jitter / ppp, y * scale - jitter / ppp);
+            let rect = drag_to_card_rect(img_rect, scale, at(20.0, 30.0, 0.4), at(35.0, 40.0, -0.4), [100, 100]);
+            assert_eq!(rect, [20, 30, 15, 10], "at {ppp}x");
+            // dragging up-left gives the same rectangle
+            let rect = drag_to_card_rect(img_rect, scale, at(35.0, 40.0, 0.0), at(20.0, 30.0, 0.0), [100, 100]);
+            assert_eq!(rect, [20, 30, 15, 10], "reversed drag at {ppp}x");
+        }
+    }
+
+    #[test]
+    fn clamp_card_size_leaves_valid_sizes_alone() {
+        assert_eq!(clamp_card_size(535, 752), ([535, 752], false), "valid size was changed");
+        assert_eq!(clamp_card_size(1, MAX_CARD_SIDE), ([1, MAX_CARD_SIDE], false), "bounds are inclusive");
+    }
+
+    #[test]
+    fn clamp_card_size_fixes_pathological_values() {
+        assert_eq!(clamp_card_size(0, 0), ([1, 1], true), "zero size must become 1x1");
+        assert_eq!(clamp_card_size(usize::MAX, 10), ([MAX_CARD_SIDE, 10], true), "huge width must be capped");
+    }
+
+    #[test]
+    fn bad_persisted_card_size_is_clamped_with_warning() {
+        let mut app: TemplateApp = serde_json::from_str(r#"{"card_width":0,"card_height":1000000}"#)
+            .expect("state should deserialize");
+        app.validate_card_size("test");
+        assert_eq!([app.card_width, app.card_height], [1, MAX_CARD_SIDE], "card size not clamped");
+        assert!(app.warning.is_some(), "clamping should leave a warning");
+    }
+
+    #[test]
+    fn bad_persisted_layout_is_clamped_with_warning() {
+        let mut app: TemplateApp = serde_json::from_str(
+            r#"{"card_width":10,"card_height":10,"margin_x":18446744073709551615,"spacing_y":5000,"offset_x":-100000,"card_pitch":[0.0,10.0]}"#,
+        )
+        .expect("state should deserialize");
+        app.validate_layout("test");
+        assert_eq!([app.margin_x, app.spacing_y], [MAX_CARD_SIDE, MAX_CARD_SIDE], "gaps not capped");
+        assert_eq!(app.offset_x, -(MAX_CARD_SIDE as i32), "offset not clamped");
+        assert_eq!(app.card_pitch, None, "unusable pitch not dropped");
+        assert!(app.warning.is_some(), "clamping should leave a warning");
+
+        let mut valid = TemplateApp { margin_x: 5, spacing_x: 2, offset_y: -3, card_pitch: Some([534.7, 752.0]), ..Default::default() };
+        valid.validate_layout("test");
+        assert!(valid.warning.is_none(), "a valid layout must not warn");
+    }
+
+    #[test]
+    fn persisted_presets_keep_their_index_and_are_validated() {
+        let mut app: TemplateApp = serde_json::from_str(
+            r#"{"selected_preset":2,"user_presets":[["  ",1,1],["Path (1380x912)",1,1],["Huge",0,99999],["Huge",5,5]]}"#,
+        )
+        .expect("state should deserialize");
+        app.validate_presets("test");
+        assert_eq!(app.card_formats().get(2).map(|f| f.0.as_str()), Some("Path (1380x912)"), "built-in presets moved");
+        assert_eq!(app.selected_preset, Some(2), "selection of a built-in preset was lost");
+        assert_eq!(app.user_presets, vec![("Huge".to_owned(), 1, MAX_CARD_SIDE)], "bad user presets not fixed");
+        assert!(app.warning.is_some(), "fixing presets should leave a warning");
+    }
+
+    #[test]
+    fn layout_math_survives_zero_card_size() {
+        let app = TemplateApp { atlas_size: [1000, 1000], card_width: 0, card_height: 0, ..Default::default() };
+        assert_eq!((app.cols(), app.rows(), app.max_index()), (0, 0, 0), "zero card size must not divide by zero");
+    }
+
+    #[test]
+    fn center_on_region_needs_a_selection_in_atlas_orientation() {
+        let mut app = TemplateApp { regions: vec![Region::new("a".to_owned(), [0, 0, 10, 10])], ..Default::default() };
+        app.request_center_on_region();
+        assert!(!app.center_on_region, "nothing selected to center on");
+        app.select_only(0);
+        app.card_rotation = 90;
+        app.request_center_on_region();
+        assert!(!app.center_on_region, "a rotated card does not line up with the regions");
+        app.card_rotation = 0;
+        app.request_center_on_region();
+        assert!(app.center_on_region, "centering not requested");
+    }
+
+    #[test]
+    fn clicks_cycle_through_overlapping_regions() {
+        let mut app = TemplateApp {
+            regions: vec![Region::new("big".to_owned(), [0, 0, 100, 100]), Region::new("small".to_owned(), [10, 10, 5, 5])],
+            ..Default::default()
+        };
+        app.click_region(12, 12, false);
+        assert_eq!(app.selected_region, Some(0), "the first region under the pointer is picked first");
+        app.click_region(12, 12, false);
+        assert_eq!(app.selected_region, Some(1), "a second click reaches the region underneath");
+        app.click_region(12, 12, false);
+        assert_eq!(app.selected_region, Some(0), "clicking again wraps around");
+        app.click_region(50, 50, false);
+        assert_eq!(app.selected_region, Some(0), "a single hit stays selected");
+        app.click_region(200, 200, false);
+        assert_eq!(app.selected_region, None, "empty space deselects");
+    }
+
+    #[test]
+    fn overlapping_pairs_ignores_touching_regions() {
+        let region = |x, y| Region::new(String::new(), [x, y, 10, 10]);
+        let app = TemplateApp { regions: vec![region(0, 0), region(10, 0), region(5, 5)], ..Default::default() };
+        assert_eq!(app.overlapping_pairs(), vec![(0, 2), (1, 2)], "edge-sharing regions must not count as overlapping");
+    }
+
+    #[test]
+    fn leftover_pixels_accounts_for_margins_and_spacing() {
+        let app = TemplateApp {
+            atlas_size: [20 + 3 * 100 + 2 * 4 + 7, 10 + 2 * 50 + 6 + 10],
+            card_width: 100,
+            card_height: 50,
+            margin_x: 20,
+            margin_y: 10,
+            spacing_x: 4,
+            spacing_y: 6,
+            ..Default::default()
+        };
+        assert_eq!((app.cols(), app.rows()), (3, 2), "grid with margins and spacing");
+        assert_eq!(app.leftover_pixels(), [7, 10], "leftover is what follows the last card");
+    }
+
+    #[test]
+    fn rescale_regions_follows_card_size() {
+        let mut app = TemplateApp { regions: vec![Region::new("title".to_owned(), [100, 50, 200, 100])], ..Default::default() };
+        app.rescale_regions((500, 500), (1000, 250));
+        let r = app.regions.first().map(|r| [r.x, r.y, r.width, r.height]);
+        assert_eq!(r, Some([200, 25, 400, 50]), "region must scale with the card");
+    }
+
+    #[test]
+    fn blank_card_notes_are_dropped() {
+        let mut app = TemplateApp::default();
+        app.set_card_note(3, "check the border".to_owned());
+        app.set_card_note(5, "typo".to_owned());
+        app.set_card_note(5, "  \n".to_owned());
+        assert_eq!(app.card_notes.len(), 1, "clearing a note removes its entry");
+        assert_eq!(app.card_notes.get(&3).map(String::as_str), Some("check the border"), "other notes stay");
+    }
+
+    #[test]
+    fn regions_file_carries_its_version() {
+        let mut app = TemplateApp { card_width: 100, card_height: 80, ..Default::default() };
+        app.regions = vec![Region::new("title".to_owned(), [1, 2, 3, 4])];
+        let json = app.regions_file_json();
+        assert!(json.as_ref().is_ok_and(|j| j.contains(&format!("\"version\": {REGIONS_FILE_VERSION}"))), "saved files name their version");
+
+        let mut loaded = TemplateApp::default();
+        loaded.apply_regions_file(&json.unwrap_or_default());
+        assert_eq!(loaded.regions.len(), 1, "current version loads");
+
+        loaded.apply_regions_file(r#"{"version": 99, "image_size": [1, 1], "regions": []}"#);
+        assert!(loaded.error.as_deref().is_some_and(|e| e.contains("version 99")), "newer files are refused by version");
+        assert_eq!(loaded.regions.len(), 1, "a refused file leaves the regions alone");
+    }
+
+    #[test]
+    fn loupe_is_clipped_at_the_card_corner() {
+        let half = LOUPE_SIDE / LOUPE_ZOOM / 2.0;
+        let (src, dest) = loupe_source(egui::pos2(10.0, 20.0), egui::vec2(100.0, 100.0));
+        assert_eq!(src, egui::Rect::from_center_size(egui::pos2(10.0, 20.0), egui::Vec2::splat(2.0 * half)), "inside the card nothing is clipped");
+        assert_eq!(dest, egui::Rect::from_min_size(egui::Pos2::ZERO, egui::Vec2::splat(LOUPE_SIDE)), "the whole box is filled");
+
+        let (src, dest) = loupe_source(egui::pos2(1.0, 99.0), egui::vec2(100.0, 100.0));
+        assert_eq!(src, egui::Rect::from_min_max(egui::pos2(0.0, 99.0 - half), egui::pos2(1.0 + half, 100.0)), "source stays on the card");
+        assert_eq!(dest.min, egui::pos2((half - 1.0) * LOUPE_ZOOM, 0.0), "the clipped part is left empty");
+        assert_eq!(dest.size(), src.size() * LOUPE_ZOOM, "source is magnified");
+    }
+
+    #[test]
+    fn clamp_clips_regions_past_the_card() {
+        let mut app = TemplateApp { card_width: 100, card_height: 80, ..Default::default() };
+        app.regions = vec![
+            Region::new("inside".to_owned(), [10, 10, 20, 20]),
+            Region::new("overhang".to_owned(), [90, 70, 30, 30]),
+            Region::new("off".to_owned(), [150, 10, 5, 5]),
+        ];
+        app.clamp_regions_to_card();
+        let rects: Vec<_> = app.regions.iter().map(|r| [r.x, r.y, r.width, r.height]).collect();
+        assert_eq!(rects, [[10, 10, 20, 20], [90, 70, 10, 10], [99, 10, 1, 5]], "only offending regions are clipped");
+        assert!(!app.regions.iter().any(|r| r.out_of_bounds([100, 80])), "all regions fit afterwards");
+    }
+
+    #[test]
+    fn parse_card_ref_accepts_index_and_row_col() {
+        assert_eq!(parse_card_ref(" 7 ", 4, 3), Ok(7), "flat index");
+        assert_eq!(parse_card_ref("2, 1", 4, 3), Ok(9), "row,col maps to row*cols+col");
+        assert_eq!(parse_card_ref("9,9", 4, 3), Ok(11), "row and col are clamped to the grid");
+        assert!(parse_card_ref("a,b", 4, 3).is_err(), "garbage must be rejected");
+    }
+
+    #[test]
+    fn regions_from_csv_skips_header_and_reads_quoted_names() {
+        let csv = "name,x,y,width,height\n\"cost, top\",1,2,30,40\r\ntitle,5,6,70,8\n";
+        let (regions, errors) = regions_from_csv(csv).expect("valid CSV");
+        assert!(errors.is_empty(), "valid CSV should parse: {errors:?}");
+        assert_eq!(regions.len(), 2, "header must not become a region");
+        assert_eq!(regions.first().map(|r| r.name.as_str()), Some("cost, top"), "quoted name keeps its comma");
+        assert_eq!(regions.get(1).map(|r| [r.x, r.y, r.width, r.height]), Some([5, 6, 70, 8]), "numbers parsed");
+    }
+
+    #[test]
+    fn malformed_csv_rows_are_reported_but_valid_rows_kept() {
+        let (regions, errors) = regions_from_csv("a,1,2,3,4\nb,1,2\nc,x,2,3,4\nd,5,6,7,8").expect("some rows are valid");
+        assert_eq!(regions.len(), 2, "valid rows should still be imported");
+        assert_eq!(errors.len(), 2, "each malformed row should be reported");
+    }
+
+    #[test]
+    fn csv_without_valid_rows_is_an_error() {
+        let err = regions_from_csv("name,x,y,width,height\nb,1,2\nc,x,2,3,4").expect_err("nothing to import");
+        assert!(err.contains("line 2") && err.contains("line 3"), "row errors should be listed: {err}");
+        assert!(regions_from_csv("name,x,y,width,height\n").is_err(), "a header alone imports nothing");
+    }
+}
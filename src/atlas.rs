@@ -0,0 +1,523 @@
+// Atlas slicing without any UI: where each card of a regular grid sits and how to cut it out.
+// The viewer keeps its layout in persisted settings and builds a `GridLayout` from them; scripts
+// and tests can use `AtlasGrid` directly on a decoded image.
+
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+
+/// Upper bound for card width/height, margins and spacing; anything larger is treated as corrupt input.
+pub const MAX_CARD_SIDE: usize = 4096;
+
+/// Card grid of an atlas: card size, empty border before the first card and gap between cards, all
+/// in pixels, plus the atlas size the grid is laid over.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GridLayout {
+    pub atlas_size: [usize; 2],
+    pub card_width: usize,
+    pub card_height: usize,
+    pub margin_x: usize,
+    pub margin_y: usize,
+    pub spacing_x: usize,
+    pub spacing_y: usize,
+    /// Shift of the whole grid on top of the margin, for sheets that are a few pixels off; negative
+    /// moves it left / up. The grid never starts before the atlas edge.
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// Exact card size for atlases scaled to fractional cells (e.g. 534.7 px). Ignored unless it
+    /// rounds to `card_width` x `card_height`.
+    pub card_pitch: Option<[f32; 2]>,
+}
+
+impl GridLayout {
+    /// Number of whole cards of size `card` that fit along an atlas axis of length `len`, given the
+    /// leading margin and the gap between cards. A trailing margin is simply left over.
+    fn cards_along(len: usize, card: usize, margin: usize, spacing: usize) -> usize {
+        if card == 0 {
+            return 0;
+        }
+        let Some(first_end) = margin.checked_add(card).filter(|&end| end <= len) else {
+            return 0;
+        };
+        // A step past the range of `usize` leaves room for the first card only
+        card.checked_add(spacing)
+            .map_or(1, |step| (len - first_end) / step + 1)
+    }
+
+    /// Like `cards_along`, but for a fractional `step` between card starts.
+    fn cards_along_step(len: usize, card: usize, margin: usize, step: f32) -> usize {
+        if card == 0 || step <= 0.0 || margin.checked_add(card).is_none_or(|end| end > len) {
+            return 0;
+        }
+        // Placement rounds, so the last card may fit although the plain division falls just short;
+        // round up to possibly one too many and drop the last while it does not fit
+        let mut n = ((len - margin - card) as f32 / step).round() as usize + 1;
+        while n > 0
+            && margin
+                .saturating_add(((n - 1) as f32 * step).round() as usize)
+                .saturating_add(card)
+                > len
+        {
+            n -= 1;
+        }
+        n
+    }
+
+    /// Distance between the left / top edges of neighbouring cards. Fractional when `card_pitch`
+    /// is set and still rounds to the card size (a preset or typed size overrides it).
+    fn card_step(&self) -> [f32; 2] {
+        let size = [self.card_width, self.card_height];
+        let pitch = self
+            .card_pitch
+            .filter(|p| p[0].round() as usize == size[0] && p[1].round() as usize == size[1])
+            .unwrap_or([size[0] as f32, size[1] as f32]);
+        [
+            pitch[0] + self.spacing_x as f32,
+            pitch[1] + self.spacing_y as f32,
+        ]
+    }
+
+    /// Atlas pixel where the first card starts: the margin moved by the offset, at least 0.
+    fn grid_start(&self) -> [usize; 2] {
+        [
+            self.margin_x.saturating_add_signed(self.offset_x as isize),
+            self.margin_y.saturating_add_signed(self.offset_y as isize),
+        ]
+    }
+
+    pub fn cols(&self) -> usize {
+        let start = self.grid_start()[0];
+        if self.card_pitch.is_none() {
+            return Self::cards_along(self.atlas_size[0], self.card_width, start, self.spacing_x);
+        }
+        Self::cards_along_step(
+            self.atlas_size[0],
+            self.card_width,
+            start,
+            self.card_step()[0],
+        )
+    }
+
+    pub fn rows(&self) -> usize {
+        let start = self.grid_start()[1];
+        if self.card_pitch.is_none() {
+            return Self::cards_along(self.atlas_size[1], self.card_height, start, self.spacing_y);
+        }
+        Self::cards_along_step(
+            self.atlas_size[1],
+            self.card_height,
+            start,
+            self.card_step()[1],
+        )
+    }
+
+    /// Index of the last card (0 when no card fits).
+    pub fn max_index(&self) -> usize {
+        (self.cols() * self.rows()).saturating_sub(1)
+    }
+
+    /// Atlas pixel position of the top-left corner of the card at `col`, `row`. With a fractional
+    /// `card_pitch` each origin is rounded on its own, so column N lands on `round(N * step)`.
+    /// Origins past the range of `usize` come out as `usize::MAX`, outside any atlas.
+    pub fn card_origin(&self, col: usize, row: usize) -> [usize; 2] {
+        let [x0, y0] = self.grid_start();
+        if self.card_pitch.is_none() {
+            let along = |start: usize, i: usize, card: usize, spacing: usize| {
+                if i == 0 {
+                    return start;
+                }
+                card.checked_add(spacing)
+                    .and_then(|step| step.checked_mul(i))
+                    .and_then(|offset| start.checked_add(offset))
+                    .unwrap_or(usize::MAX)
+            };
+            return [
+                along(x0, col, self.card_width, self.spacing_x),
+                along(y0, row, self.card_height, self.spacing_y),
+            ];
+        }
+        let [sx, sy] = self.card_step();
+        [
+            x0.checked_add((col as f32 * sx).round() as usize)
+                .unwrap_or(usize::MAX),
+            y0.checked_add((row as f32 * sy).round() as usize)
+                .unwrap_or(usize::MAX),
+        ]
+    }
+
+    /// Index of the card containing atlas pixel `[x, y]` (the inverse of `card_origin`), or
+    /// `None` for pixels before the grid start, a gap between cards or past the last whole card.
+    pub fn card_at(&self, [x, y]: [usize; 2]) -> Option<usize> {
+        let [sx, sy] = self.card_step();
+        // With a fractional pitch the rounded origins can be a pixel off the estimate either way, and
+        // neighbouring cards may share their edge pixel; that pixel goes to the later card
+        let axis = |p: usize,
+                    margin: usize,
+                    step: f32,
+                    size: usize,
+                    count: usize,
+                    origin: &dyn Fn(usize) -> usize| {
+            let est = (p.checked_sub(margin)? as f32 / step) as usize;
+            [Some(est + 1), Some(est), est.checked_sub(1)]
+                .into_iter()
+                .flatten()
+                .find(|&i| i < count && (origin(i)..origin(i).saturating_add(size)).contains(&p))
+        };
+        let [x0, y0] = self.grid_start();
+        let col = axis(x, x0, sx, self.card_width, self.cols(), &|c| {
+            self.card_origin(c, 0)[0]
+        })?;
+        let row = axis(y, y0, sy, self.card_height, self.rows(), &|r| {
+            self.card_origin(0, r)[1]
+        })?;
+        Some(row * self.cols() + col)
+    }
+
+    /// Rectangle of card `index` (row-major) in the atlas as `[x, y, width, height]`, or `None`
+    /// if it does not lie entirely within the atlas.
+    pub fn card_rect(&self, index: usize) -> Option<[usize; 4]> {
+        let cols = self.cols();
+        if cols == 0 {
+            return None;
+        }
+        let [x, y] = self.card_origin(index % cols, index / cols);
+        let fits = |start: usize, size: usize, len: usize| {
+            start.checked_add(size).is_some_and(|end| end <= len)
+        };
+        if !fits(x, self.card_width, self.atlas_size[0])
+            || !fits(y, self.card_height, self.atlas_size[1])
+        {
+            return None;
+        }
+        Some([x, y, self.card_width, self.card_height])
+    }
+}
+
+/// An atlas image together with its card grid.
+pub struct AtlasGrid<'a> {
+    image: &'a RgbaImage,
+    layout: GridLayout,
+}
+
+impl<'a> AtlasGrid<'a> {
+    /// Lay `layout` over `image`; the layout's atlas size is taken from the image.
+    pub fn new(image: &'a RgbaImage, layout: GridLayout) -> Self {
+        let atlas_size = [image.width() as usize, image.height() as usize];
+        Self {
+            image,
+            layout: GridLayout {
+                atlas_size,
+                ..layout
+            },
+        }
+    }
+
+    pub fn layout(&self) -> &GridLayout {
+        &self.layout
+    }
+
+    pub fn cols(&self) -> usize {
+        self.layout.cols()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.layout.rows()
+    }
+
+    pub fn max_index(&self) -> usize {
+        self.layout.max_index()
+    }
+
+    /// Cut card `index` (row-major) out of the atlas as a standalone image.
+    pub fn card(&self, index: usize) -> Option<RgbaImage> {
+        let [x, y, w, h] = self.layout.card_rect(index)?;
+        Some(
+            image::imageops::crop_imm(self.image, x as u32, y as u32, w as u32, h as u32)
+                .to_image(),
+        )
+    }
+
+    /// Whether every pixel of card `index` is fully transparent (a gap in the atlas).
+    pub fn is_card_empty(&self, index: usize) -> bool {
+        let Some([x, y, w, h]) = self.layout.card_rect(index) else {
+            return false;
+        };
+        let stride = self.image.width() as usize * 4;
+        let raw = self.image.as_raw();
+        (y..y + h).all(|row| {
+            raw.get(row * stride + x * 4..row * stride + (x + w) * 4)
+                .is_some_and(|px| px.iter().skip(3).step_by(4).all(|&alpha| alpha == 0))
+        })
+    }
+
+    /// Number of fully transparent cards. Reads every card, so it is slow on large atlases.
+    pub fn empty_cards(&self) -> usize {
+        if self.cols() == 0 {
+            return 0;
+        }
+        (0..=self.max_index())
+            .filter(|&i| self.is_card_empty(i))
+            .count()
+    }
+}
+
+/// Margin or spacing in a sidecar file: one number for both axes, or `[x, y]`.
+#[derive(serde::Deserialize, Clone, Copy)]
+#[serde(untagged)]
+enum SidecarGap {
+    Both(usize),
+    Axes([usize; 2]),
+}
+
+impl Default for SidecarGap {
+    fn default() -> Self {
+        Self::Both(0)
+    }
+}
+
+impl SidecarGap {
+    fn axes(self) -> [usize; 2] {
+        match self {
+            Self::Both(v) => [v, v],
+            Self::Axes(a) => a,
+        }
+    }
+}
+
+/// Card layout shipped next to an atlas as JSON:
+/// `{"card_width": 535, "card_height": 752, "margin": 0, "spacing": [2, 4]}`.
+#[derive(serde::Deserialize)]
+struct Sidecar {
+    card_width: usize,
+    card_height: usize,
+    #[serde(default)]
+    margin: SidecarGap,
+    #[serde(default)]
+    spacing: SidecarGap,
+}
+
+/// Parse the text of a sidecar file into a layout (without atlas size). `None` if it is not one,
+/// has an empty card or a card side, margin or gap above `MAX_CARD_SIDE`.
+pub fn parse_sidecar(text: &str) -> Option<GridLayout> {
+    let s: Sidecar = serde_json::from_str(text).ok()?;
+    if s.card_width == 0 || s.card_height == 0 {
+        return None;
+    }
+    let ([margin_x, margin_y], [spacing_x, spacing_y]) = (s.margin.axes(), s.spacing.axes());
+    let sides = [
+        s.card_width,
+        s.card_height,
+        margin_x,
+        margin_y,
+        spacing_x,
+        spacing_y,
+    ];
+    if sides.iter().any(|&v| v > MAX_CARD_SIDE) {
+        return None;
+    }
+    Some(GridLayout {
+        card_width: s.card_width,
+        card_height: s.card_height,
+        margin_x,
+        margin_y,
+        spacing_x,
+        spacing_y,
+        ..Default::default()
+    })
+}
+
+/// Find and parse the sidecar of the atlas at `atlas`: `<atlas>.json` or `<atlas>.meta` (e.g.
+/// `light_cards.png.json`). Missing, unreadable and malformed files are skipped.
+pub fn read_sidecar(atlas: &Path) -> Option<(PathBuf, GridLayout)> {
+    ["json", "meta"].into_iter().find_map(|ext| {
+        let mut name = atlas.as_os_str().to_owned();
+        name.push(format!(".{ext}"));
+        let path = PathBuf::from(name);
+        let layout = parse_sidecar(&std::fs::read_to_string(&path).ok()?)?;
+        Some((path, layout))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atlas_grid_cuts_cards_headless() {
+        // 2×2 cards of 3×2 px with a 1 px margin and gap; each pixel holds its atlas x, y
+        let img = image::RgbaImage::from_fn(9, 7, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let layout = GridLayout {
+            card_width: 3,
+            card_height: 2,
+            margin_x: 1,
+            margin_y: 1,
+            spacing_x: 1,
+            spacing_y: 1,
+            ..Default::default()
+        };
+        let grid = AtlasGrid::new(&img, layout);
+        assert_eq!([grid.cols(), grid.rows(), grid.max_index()], [2, 2, 3]);
+        let card = grid.card(3).expect("last card missing");
+        assert_eq!(card.dimensions(), (3, 2));
+        assert_eq!(
+            card.get_pixel(0, 0).0,
+            [5, 4, 0, 255],
+            "card 3 cut from the wrong place"
+        );
+        assert!(grid.card(4).is_none());
+    }
+
+    #[test]
+    fn card_at_inverts_card_origin() {
+        let layout = GridLayout {
+            atlas_size: [1000, 500],
+            card_width: 100,
+            card_height: 200,
+            margin_x: 5,
+            margin_y: 5,
+            spacing_x: 10,
+            spacing_y: 10,
+            ..Default::default()
+        };
+        let cols = layout.cols();
+        for i in 0..=layout.max_index() {
+            let [x, y] = layout.card_origin(i % cols, i / cols);
+            assert_eq!(layout.card_at([x, y]), Some(i), "top-left of card {i}");
+            assert_eq!(
+                layout.card_at([x + 99, y + 199]),
+                Some(i),
+                "bottom-right of card {i}"
+            );
+        }
+        assert_eq!(layout.card_at([2, 50]), None, "margin");
+        assert_eq!(layout.card_at([108, 50]), None, "gap between columns");
+        assert_eq!(
+            layout.card_at([999, 50]),
+            None,
+            "past the last whole column"
+        );
+
+        let fractional = GridLayout {
+            atlas_size: [5347, 752],
+            card_width: 535,
+            card_height: 752,
+            card_pitch: Some([534.7, 752.0]),
+            ..Default::default()
+        };
+        assert_eq!(fractional.card_at([4812, 0]), Some(9));
+        assert_eq!(fractional.card_at([4811, 0]), Some(8));
+    }
+
+    #[test]
+    fn huge_margins_and_spacing_do_not_overflow() {
+        let layout = GridLayout {
+            atlas_size: [100, 100],
+            card_width: 10,
+            card_height: 10,
+            margin_x: usize::MAX,
+            ..Default::default()
+        };
+        assert_eq!(layout.cols(), 0, "a margin past the atlas leaves no column");
+        let layout = GridLayout {
+            atlas_size: [100, 100],
+            card_width: 10,
+            card_height: 10,
+            spacing_x: usize::MAX,
+            spacing_y: usize::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            [layout.cols(), layout.rows()],
+            [1, 1],
+            "a huge gap leaves the first card only"
+        );
+        assert_eq!(
+            layout.card_origin(1, 2),
+            [usize::MAX, usize::MAX],
+            "an origin past usize must saturate"
+        );
+        assert_eq!(
+            layout.card_rect(1),
+            None,
+            "the second card lies outside the atlas"
+        );
+    }
+
+    #[test]
+    fn offset_shifts_the_grid_within_the_atlas() {
+        let layout = GridLayout {
+            atlas_size: [100, 50],
+            card_width: 30,
+            card_height: 50,
+            margin_x: 5,
+            ..Default::default()
+        };
+        assert_eq!(layout.cols(), 3, "three cards fit after the margin");
+        let shifted = GridLayout {
+            offset_x: 3,
+            ..layout
+        };
+        assert_eq!(
+            shifted.card_origin(1, 0),
+            [38, 0],
+            "the offset adds to the margin"
+        );
+        assert_eq!(
+            shifted.card_at([38, 0]),
+            Some(1),
+            "clicks follow the shifted grid"
+        );
+        let pushed = GridLayout {
+            offset_x: 20,
+            ..layout
+        };
+        assert_eq!(pushed.cols(), 2, "a card pushed past the edge is dropped");
+        let back = GridLayout {
+            offset_x: -10,
+            ..layout
+        };
+        assert_eq!(
+            back.card_origin(0, 0),
+            [0, 0],
+            "the grid never starts before the atlas"
+        );
+    }
+
+    #[test]
+    fn sidecar_accepts_single_and_per_axis_gaps() {
+        let layout = parse_sidecar(
+            r#"{"card_width": 535, "card_height": 752, "margin": 3, "spacing": [2, 4]}"#,
+        );
+        assert_eq!(
+            layout.map(|l| [
+                l.card_width,
+                l.card_height,
+                l.margin_x,
+                l.margin_y,
+                l.spacing_x,
+                l.spacing_y
+            ]),
+            Some([535, 752, 3, 3, 2, 4]),
+            "one number applies to both axes"
+        );
+        assert!(
+            parse_sidecar(r#"{"frames": {}}"#).is_none(),
+            "other JSON is not a sidecar"
+        );
+        assert!(
+            parse_sidecar(r#"{"card_width": 0, "card_height": 10}"#).is_none(),
+            "empty cards are rejected"
+        );
+        assert!(
+            parse_sidecar(
+                r#"{"card_width": 10, "card_height": 10, "margin": [18446744073709551615, 0]}"#
+            )
+            .is_none(),
+            "out-of-range margins are rejected"
+        );
+        assert!(
+            parse_sidecar(r#"{"card_width": 10, "card_height": 10, "spacing": 5000}"#).is_none(),
+            "out-of-range spacing is rejected"
+        );
+    }
+}
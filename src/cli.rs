@@ -0,0 +1,257 @@
+// Command-line slicing: cut every card of an atlas into its own PNG without opening the window.
+//
+//   wotr_helper --slice atlas.png --card 535x752 [--margin 0x0] [--spacing 0x0] [--out ./cards]
+
+use std::path::PathBuf;
+
+use crate::atlas::{AtlasGrid, GridLayout, MAX_CARD_SIDE};
+
+pub const USAGE: &str = "\
+Usage: wotr_helper --slice <atlas> --card <W>x<H> [--margin <X>x<Y>] [--spacing <X>x<Y>] [--out <dir>]
+
+Writes every card of the atlas to <dir>/card_NNNN.png (default: the current directory).
+Without --slice the viewer window opens.";
+
+#[derive(Debug, PartialEq, Eq)]
+struct SliceArgs {
+    atlas: PathBuf,
+    card: [usize; 2],
+    margin: [usize; 2],
+    spacing: [usize; 2],
+    out: PathBuf,
+}
+
+/// "535x752" -> [535, 752]
+fn parse_size(flag: &str, s: &str) -> Result<[usize; 2], String> {
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("{flag} expects <X>x<Y>, got '{s}'"))?;
+    let num = |v: &str| {
+        v.trim()
+            .parse::<usize>()
+            .map_err(|e| format!("{flag}: '{v}' is not a whole number ({e})"))
+    };
+    Ok([num(w)?, num(h)?])
+}
+
+/// Like `parse_size`, with each side in `min..=MAX_CARD_SIDE` pixels (cards are at least 1, margins
+/// and gaps may be 0).
+fn parse_bounded(flag: &str, s: &str, min: usize) -> Result<[usize; 2], String> {
+    let size = parse_size(flag, s)?;
+    if size.iter().any(|&v| v < min) {
+        return Err(format!("{flag}: width and height must be at least {min}"));
+    }
+    if size.iter().any(|&v| v > MAX_CARD_SIDE) {
+        return Err(format!(
+            "{flag}: '{s}' exceeds the limit of {MAX_CARD_SIDE} pixels"
+        ));
+    }
+    Ok(size)
+}
+
+/// Whether `args` (without the program name) ask for the command-line mode rather than the
+/// window: `--slice` or `--help`. Anything else is left to the viewer.
+pub fn wants_cli(args: &[String]) -> bool {
+    args.iter()
+        .any(|a| matches!(a.as_str(), "--slice" | "--help" | "-h"))
+}
+
+fn parse_args(args: &[String]) -> Result<SliceArgs, String> {
+    let (mut atlas, mut card) = (None, None);
+    let (mut margin, mut spacing) = ([0, 0], [0, 0]);
+    let mut out = PathBuf::from(".");
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{flag} needs a value"));
+        match flag.as_str() {
+            "--slice" => atlas = Some(PathBuf::from(value()?)),
+            "--card" => card = Some(parse_bounded(flag, value()?, 1)?),
+            "--margin" => margin = parse_bounded(flag, value()?, 0)?,
+            "--spacing" => spacing = parse_bounded(flag, value()?, 0)?,
+            "--out" => out = PathBuf::from(value()?),
+            other => return Err(format!("unknown argument '{other}'")),
+        }
+    }
+    let atlas = atlas.ok_or("--slice <atlas> is required")?;
+    let card = card.ok_or("--card <W>x<H> is required")?;
+    Ok(SliceArgs {
+        atlas,
+        card,
+        margin,
+        spacing,
+        out,
+    })
+}
+
+/// Run the command line given by `args` (without the program name), printing progress to stdout.
+///
+/// # Errors
+/// Bad arguments (the message ends with the usage text), an atlas that cannot be read or holds no
+/// whole card, and cards that cannot be written.
+#[expect(
+    clippy::print_stdout,
+    reason = "progress output of the command-line mode"
+)]
+pub fn run(args: &[String]) -> Result<(), String> {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let args = parse_args(args).map_err(|e| format!("{e}\n\n{USAGE}"))?;
+
+    let atlas =
+        crate::app::decode_atlas(|| std::fs::File::open(&args.atlas).map(std::io::BufReader::new))
+            .map_err(|e| format!("failed to load atlas '{}': {e}", args.atlas.display()))?;
+    let layout = GridLayout {
+        card_width: args.card[0],
+        card_height: args.card[1],
+        margin_x: args.margin[0],
+        margin_y: args.margin[1],
+        spacing_x: args.spacing[0],
+        spacing_y: args.spacing[1],
+        ..Default::default()
+    };
+    let grid = AtlasGrid::new(&atlas, layout);
+    let count = grid.cols() * grid.rows();
+    if count == 0 {
+        return Err(format!(
+            "no {}×{} card fits in the {}×{} atlas",
+            args.card[0],
+            args.card[1],
+            atlas.width(),
+            atlas.height()
+        ));
+    }
+    println!(
+        "{}: {} cols × {} rows",
+        args.atlas.display(),
+        grid.cols(),
+        grid.rows()
+    );
+
+    std::fs::create_dir_all(&args.out)
+        .map_err(|e| format!("cannot create '{}': {e}", args.out.display()))?;
+    for index in 0..count {
+        let Some(card) = grid.card(index) else {
+            continue;
+        };
+        let path = args.out.join(format!("card_{index:04}.png"));
+        card.save(&path)
+            .map_err(|e| format!("failed to write '{}': {e}", path.display()))?;
+        println!("[{}/{count}] {}", index + 1, path.display());
+    }
+    println!("Wrote {count} cards to {}", args.out.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn all_flags_are_read() {
+        let parsed = parse_args(&args(
+            "--slice a.png --card 535x752 --margin 3X4 --spacing 1x2 --out cards",
+        ));
+        assert_eq!(
+            parsed,
+            Ok(SliceArgs {
+                atlas: PathBuf::from("a.png"),
+                card: [535, 752],
+                margin: [3, 4],
+                spacing: [1, 2],
+                out: PathBuf::from("cards"),
+            }),
+            "every flag should land in its field"
+        );
+    }
+
+    #[test]
+    fn gaps_and_output_have_defaults() {
+        let parsed = parse_args(&args("--card 10x20 --slice a.png"))
+            .expect("minimal arguments should parse");
+        assert_eq!(
+            [parsed.margin, parsed.spacing],
+            [[0, 0], [0, 0]],
+            "no margin or gap by default"
+        );
+        assert_eq!(
+            parsed.out,
+            PathBuf::from("."),
+            "cards go to the current directory by default"
+        );
+    }
+
+    #[test]
+    fn missing_and_unknown_arguments_are_reported() {
+        assert_eq!(
+            parse_args(&args("--card 10x20")),
+            Err("--slice <atlas> is required".to_owned()),
+            "atlas missing"
+        );
+        assert_eq!(
+            parse_args(&args("--slice a.png")),
+            Err("--card <W>x<H> is required".to_owned()),
+            "card size missing"
+        );
+        assert_eq!(
+            parse_args(&args("--slice a.png --card")),
+            Err("--card needs a value".to_owned()),
+            "flag without value"
+        );
+        assert_eq!(
+            parse_args(&args("--slice a.png --bogus 1")),
+            Err("unknown argument '--bogus'".to_owned()),
+            "unknown flag"
+        );
+    }
+
+    #[test]
+    fn sizes_are_checked() {
+        let err = |line: &str| parse_args(&args(line)).expect_err("arguments should be rejected");
+        assert_eq!(
+            err("--slice a --card 535"),
+            "--card expects <X>x<Y>, got '535'",
+            "no separator"
+        );
+        assert!(
+            err("--slice a --card 535xabc").starts_with("--card: 'abc' is not a whole number"),
+            "not a number"
+        );
+        assert_eq!(
+            err("--slice a --card 0x752"),
+            "--card: width and height must be at least 1",
+            "empty card"
+        );
+        assert_eq!(
+            err("--slice a --card 5000x10"),
+            "--card: '5000x10' exceeds the limit of 4096 pixels",
+            "card too large"
+        );
+        assert_eq!(
+            err("--slice a --card 10x10 --margin 0x5000"),
+            "--margin: '0x5000' exceeds the limit of 4096 pixels",
+            "margin too large"
+        );
+        assert_eq!(
+            err("--slice a --card 10x10 --spacing 18446744073709551615x0"),
+            "--spacing: '18446744073709551615x0' exceeds the limit of 4096 pixels",
+            "gap too large"
+        );
+    }
+
+    #[test]
+    fn only_slice_and_help_select_the_command_line() {
+        assert!(
+            wants_cli(&args("--slice a.png --card 10x10")),
+            "--slice runs the command line"
+        );
+        assert!(wants_cli(&args("-h")), "-h prints the usage");
+        assert!(!wants_cli(&args("a.png")), "a bare path opens the viewer");
+        assert!(!wants_cli(&[]), "no arguments open the viewer");
+    }
+}
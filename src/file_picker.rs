@@ -13,9 +13,42 @@ mod web {
 
     static SELECTED_IMAGE: Lazy<Mutex<Option<(Vec<u8>, String)>>> = Lazy::new(|| Mutex::new(None));
 
+    // Text of a file picked with `open_text_picker`
+    static SELECTED_TEXT: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+    // Why the last `request_asset` failed, until the app picks it up
+    static ASSET_ERROR: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+    fn set_asset_error(path: &str, why: String) {
+        web_sys::console::error_1(&format!("request_asset {path}: {why}").into());
+        *ASSET_ERROR.lock().unwrap() = Some(format!("Failed to fetch '{path}': {why}"));
+    }
+
+    /// Error message of a failed `request_asset`, if any (taken, so it is reported once).
+    pub fn take_asset_error() -> Option<String> {
+        ASSET_ERROR.lock().unwrap().take()
+    }
+
     pub fn open_image_picker() {
         // Debug: log when picker is invoked (helps detect stale builds / service worker cache)
         web_sys::console::log_1(&"file_picker: open_image_picker called".into());
+        let accept: Vec<String> = crate::app::supported_extensions().iter().map(|e| format!(".{e}")).collect();
+        open_picker(&accept.join(","), |bytes, name| *SELECTED_IMAGE.lock().unwrap() = Some((bytes, name)));
+    }
+
+    /// Let the user pick a text file (regions, CSV, settings) matching `accept`, e.g. ".json"; its
+    /// text is returned later from `take_selected_text()`.
+    pub fn open_text_picker(accept: &str) {
+        open_picker(accept, |bytes, _| *SELECTED_TEXT.lock().unwrap() = Some(String::from_utf8_lossy(&bytes).into_owned()));
+    }
+
+    pub fn take_selected_text() -> Option<String> {
+        SELECTED_TEXT.lock().unwrap().take()
+    }
+
+    /// Show the browser's file dialog for files matching `accept` and pass the first picked file's
+    /// bytes and name to `on_load` once read.
+    fn open_picker(accept: &str, on_load: fn(Vec<u8>, String)) {
         let window = match web_sys::window() { Some(w) => w, None => return };
         let document = match window.document() { Some(d) => d, None => return };
 
@@ -30,7 +63,7 @@ mod web {
         };
 
         input.set_type("file");
-        input.set_accept("image/png,image/jpeg");
+        input.set_accept(accept);
         let _ = input.set_attribute("style", "position: fixed; left: -9999px; width: 1px; height: 1px; opacity: 0;");
 
         // Append to body so click is allowed
@@ -54,7 +87,7 @@ mod web {
                         let arr = Uint8Array::new(&result);
                         let mut vec = vec![0u8; arr.length() as usize];
                         arr.copy_to(&mut vec[..]);
-                        *SELECTED_IMAGE.lock().unwrap() = Some((vec, name));
+                        on_load(vec, name);
                     }) as Box<dyn FnOnce(_)>);
                     fr.set_onload(Some(onload.as_ref().unchecked_ref()));
                     onload.forget();
@@ -74,6 +107,34 @@ mod web {
         SELECTED_IMAGE.lock().unwrap().take()
     }
 
+    /// Offer `bytes` to the user as a browser download named `filename`.
+    pub fn download_bytes(bytes: &[u8], filename: &str, mime: &str) {
+        let window = match web_sys::window() { Some(w) => w, None => return };
+        let document = match window.document() { Some(d) => d, None => return };
+
+        let parts = js_sys::Array::of1(&Uint8Array::from(bytes));
+        let opts = web_sys::BlobPropertyBag::new();
+        opts.set_type(mime);
+        let blob = match web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &opts) {
+            Ok(b) => b,
+            Err(_) => return,
+        };
+        let url = match web_sys::Url::create_object_url_with_blob(&blob) {
+            Ok(u) => u,
+            Err(_) => return,
+        };
+
+        // A temporary <a download> element triggers the browser's save flow
+        if let Ok(anchor) = document.create_element("a") {
+            if let Ok(anchor) = anchor.dyn_into::<web_sys::HtmlAnchorElement>() {
+                anchor.set_href(&url);
+                anchor.set_download(filename);
+                anchor.click();
+            }
+        }
+        let _ = web_sys::Url::revoke_object_url(&url);
+    }
+
     /// Trigger an async fetch of a bundled asset (relative URL). The bytes+filename will be stored
     /// in the same internal buffer and returned later from `take_selected_image_bytes()`.
     pub fn request_asset(path: &str) {
@@ -85,6 +146,11 @@ mod web {
             match wasm_bindgen_futures::JsFuture::from(promise).await {
                 Ok(resp_val) => {
                     let resp: web_sys::Response = resp_val.dyn_into().unwrap();
+                    // fetch only rejects on network errors; a 404 page must not be decoded as an image
+                    if !resp.ok() {
+                        set_asset_error(&path, format!("HTTP {} {}", resp.status(), resp.status_text()));
+                        return;
+                    }
                     match resp.array_buffer() {
                         Ok(promise) => {
                             match wasm_bindgen_futures::JsFuture::from(promise).await {
@@ -95,26 +161,22 @@ mod web {
                                     let filename = std::path::Path::new(&path).file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or(path.clone());
                                     *SELECTED_IMAGE.lock().unwrap() = Some((vec, filename));
                                 }
-                                Err(e) => {
-                                    web_sys::console::error_1(&e);
-                                }
+                                Err(e) => set_asset_error(&path, format!("{e:?}")),
                             }
                         }
-                        Err(e) => {
-                            web_sys::console::error_1(&e);
-                        }
+                        Err(e) => set_asset_error(&path, format!("{e:?}")),
                     }
                 }
-                Err(e) => {
-                    web_sys::console::error_1(&e);
-                }
+                Err(e) => set_asset_error(&path, format!("{e:?}")),
             }
         });
     }
 }
 
 #[cfg(target_arch = "wasm32")]
-pub use web::{open_image_picker, take_selected_image_bytes, request_asset};
+pub use web::{
+    download_bytes, open_image_picker, open_text_picker, take_asset_error, take_selected_image_bytes, take_selected_text, request_asset,
+};
 
 #[cfg(not(target_arch = "wasm32"))]
 // Native stubs; native builds use rfd::FileDialog directly
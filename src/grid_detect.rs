@@ -0,0 +1,151 @@
+// Card grid detection: guess the card size of an atlas from the regular spacing of card boundaries.
+// Along each axis we measure how much every column (row) differs from its neighbour. Card edges and
+// gutters (transparent or flat-colored strips between cards) show up as the same pattern of peaks
+// in that profile on every card, so the card size is the smallest period at which it repeats. The
+// period does not have to divide the atlas: sheets often end in a partial card or a padding strip.
+
+use image::RgbaImage;
+
+// Only every STEP-th row (column) is sampled when building a profile; plenty for card-sized features
+const SAMPLE_STEP: u32 = 4;
+
+// Smallest card side considered, in pixels
+const MIN_PERIOD: usize = 8;
+
+// Minimum autocorrelation for a period to count as a grid
+const MIN_CORRELATION: f32 = 0.5;
+
+// Periods within this fraction of the best score are preferred if smaller (multiples of the real
+// period score about as well as the period itself)
+const NEAR_BEST: f32 = 0.8;
+
+fn pixel_diff(a: image::Rgba<u8>, b: image::Rgba<u8>) -> f32 {
+    a.0.iter()
+        .zip(b.0.iter())
+        .map(|(x, y)| f32::from(x.abs_diff(*y)))
+        .sum()
+}
+
+/// `profile[i]` = mean difference between line `i - 1` and line `i` (`profile[0]` is 0).
+fn profile(img: &RgbaImage, vertical_lines: bool) -> Vec<f32> {
+    let (w, h) = img.dimensions();
+    let (lines, along) = if vertical_lines { (w, h) } else { (h, w) };
+    let samples = along.div_ceil(SAMPLE_STEP).max(1) as f32;
+    let mut out = vec![0.0; lines as usize];
+    for (i, slot) in out.iter_mut().enumerate().skip(1) {
+        let i = i as u32;
+        let mut sum = 0.0;
+        for s in (0..along).step_by(SAMPLE_STEP as usize) {
+            sum += if vertical_lines {
+                pixel_diff(*img.get_pixel(i - 1, s), *img.get_pixel(i, s))
+            } else {
+                pixel_diff(*img.get_pixel(s, i - 1), *img.get_pixel(s, i))
+            };
+        }
+        *slot = sum / samples;
+    }
+    out
+}
+
+/// Best cell size along one axis: the smallest period at which the profile correlates with itself
+/// about as well as at any period; at least two cells must fit, and a remainder after the last
+/// whole cell is allowed. `None` when nothing periodic is found.
+fn period(profile: &[f32]) -> Option<usize> {
+    let n = profile.len();
+    let mean = profile.iter().sum::<f32>() / n.max(1) as f32;
+    let centered: Vec<f32> = profile.iter().map(|v| v - mean).collect();
+
+    // Normalized autocorrelation at lag p. Card edges, frames and gutters repeat on every card,
+    // so the true card size (and its multiples) correlate strongly while in-card detail does not.
+    let autocorr = |p: usize| {
+        let (mut num, mut den_a, mut den_b) = (0.0, 0.0, 0.0);
+        for (a, b) in centered.iter().zip(centered.iter().skip(p)) {
+            num += a * b;
+            den_a += a * a;
+            den_b += b * b;
+        }
+        let den = (den_a * den_b).sqrt();
+        if den <= f32::EPSILON { 0.0 } else { num / den }
+    };
+
+    let scores: Vec<(usize, f32)> = (MIN_PERIOD..=n / 2).map(|p| (p, autocorr(p))).collect();
+
+    let best = scores.iter().map(|(_, s)| *s).fold(0.0, f32::max);
+    if best < MIN_CORRELATION {
+        return None;
+    }
+    scores
+        .iter()
+        .find(|(_, s)| *s >= best * NEAR_BEST)
+        .map(|(p, _)| *p)
+}
+
+/// Guess `(card_width, card_height)` for an atlas. An axis without periodic structure is taken to
+/// hold a single card (e.g. a one-row strip), but at least one axis must show a grid.
+pub fn detect_card_size(img: &RgbaImage) -> Option<(usize, usize)> {
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    match (period(&profile(img, true)), period(&profile(img, false))) {
+        (None, None) => None,
+        (cw, ch) => Some((cw.unwrap_or(w), ch.unwrap_or(h))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Light cards of `card` pixels with a 2 px dark frame, tiled `cells` times from the top-left;
+    // pixels past the last whole card are light padding
+    fn grid(size: [u32; 2], card: [u32; 2], cells: [u32; 2]) -> RgbaImage {
+        RgbaImage::from_fn(size[0], size[1], |x, y| {
+            let inside = x < card[0] * cells[0] && y < card[1] * cells[1];
+            let frame = x % card[0] < 2 || y % card[1] < 2;
+            if inside && frame {
+                image::Rgba([20, 20, 20, 255])
+            } else {
+                image::Rgba([230, 230, 230, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn whole_grid() {
+        let img = grid([200, 180], [50, 60], [4, 3]);
+        assert_eq!(
+            detect_card_size(&img),
+            Some((50, 60)),
+            "a sheet of whole cards"
+        );
+    }
+
+    #[test]
+    fn trailing_remainder_is_allowed() {
+        // 170 = 3 × 50 + 20 and 193 = 3 × 60 + 13: neither side is a multiple of the card
+        let img = grid([170, 193], [50, 60], [3, 3]);
+        assert_eq!(
+            detect_card_size(&img),
+            Some((50, 60)),
+            "padding after the last card must not hide the grid"
+        );
+    }
+
+    #[test]
+    fn single_row_strip() {
+        let img = grid([200, 60], [50, 60], [4, 1]);
+        assert_eq!(
+            detect_card_size(&img),
+            Some((50, 60)),
+            "one row is taken to be one card high"
+        );
+    }
+
+    #[test]
+    fn flat_image_has_no_grid() {
+        let img = RgbaImage::from_pixel(120, 80, image::Rgba([10, 20, 30, 255]));
+        assert_eq!(
+            detect_card_size(&img),
+            None,
+            "nothing periodic in a flat image"
+        );
+    }
+}
@@ -1,14 +1,23 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+pub mod atlas;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
 mod file_picker;
+mod grid_detect;
+mod png_meta;
 pub use app::TemplateApp;
+#[cfg(not(target_arch = "wasm32"))]
+pub use app::WINDOW_TITLE;
 
+#[cfg(not(target_arch = "wasm32"))]
 use eframe::NativeOptions;
 
 #[cfg(target_os = "android")]
 use egui_winit::winit;
 
+#[cfg(not(target_arch = "wasm32"))]
 impl TemplateApp {
     /// Run the app with provided NativeOptions (used by Android entrypoint).
     pub fn run(options: NativeOptions) -> Result<(), eframe::Error> {
@@ -3,11 +3,28 @@
 
 // When compiling natively:
 #[cfg(not(target_arch = "wasm32"))]
-fn main() -> eframe::Result {
+#[expect(
+    clippy::print_stderr,
+    reason = "command-line mode reports failures on the terminal"
+)]
+fn main() -> std::process::ExitCode {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    // `--slice` (or `--help`) means command-line slicing; otherwise the window opens
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if wotr_helper::cli::wants_cli(&args) {
+        return match wotr_helper::cli::run(&args) {
+            Ok(()) => std::process::ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::ExitCode::from(2)
+            }
+        };
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
+            .with_title(wotr_helper::WINDOW_TITLE)
             .with_inner_size([400.0, 300.0])
             .with_min_inner_size([300.0, 220.0])
             .with_icon(
@@ -15,13 +32,22 @@ fn main() -> eframe::Result {
                 eframe::icon_data::from_png_bytes(&include_bytes!("../assets/icon-256.png")[..])
                     .expect("Failed to load icon"),
             ),
+        // The app stores and restores the window geometry itself (see `TemplateApp::new`)
+        persist_window: false,
         ..Default::default()
     };
-    eframe::run_native(
+    // The app name also names the folder the state is persisted in; renaming it would lose saved settings
+    match eframe::run_native(
         "Atlas Viewer",
         native_options,
         Box::new(|cc| Ok(Box::new(wotr_helper::TemplateApp::new(cc)))),
-    )
+    ) {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::ExitCode::FAILURE
+        }
+    }
 }
 
 // When compiling to web using trunk:
@@ -0,0 +1,215 @@
+// Card layout hints embedded in PNG text chunks (tEXt / zTXt / iTXt).
+// Some sheet exporters annotate their output with the cell size or the grid dimensions, either as
+// one chunk per value (keyword "card_width", text "535") or as a single chunk holding
+// `key=value` pairs ("Comment" -> "card_width=535; card_height=752"). Both shapes are accepted.
+
+use std::io::Read;
+
+#[derive(Default)]
+struct Hints {
+    width: Option<usize>,
+    height: Option<usize>,
+    cols: Option<usize>,
+    rows: Option<usize>,
+}
+
+impl Hints {
+    fn apply(&mut self, key: &str, value: &str) {
+        let key = key.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        let value = value.trim();
+        match key.as_str() {
+            "card_width" | "cell_width" | "frame_width" | "tile_width" => {
+                set(&mut self.width, value);
+            }
+            "card_height" | "cell_height" | "frame_height" | "tile_height" => {
+                set(&mut self.height, value);
+            }
+            "cols" | "columns" => set(&mut self.cols, value),
+            "rows" => set(&mut self.rows, value),
+            "card_size" | "cell_size" | "frame_size" | "tile_size" => {
+                if let Some((w, h)) = parse_pair(value) {
+                    self.width = Some(w);
+                    self.height = Some(h);
+                }
+            }
+            "grid" => {
+                if let Some((c, r)) = parse_pair(value) {
+                    self.cols = Some(c);
+                    self.rows = Some(r);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Feed a whole text chunk: the keyword itself may name the value, and the text may also
+    /// contain `key=value` / `key: value` pairs separated by `;`, `,` or newlines. A `,` followed by
+    /// no key belongs to the value (e.g. `card_size=535,752`).
+    fn apply_chunk(&mut self, keyword: &str, text: &str) {
+        self.apply(keyword, text);
+        for line in text.split([';', '\n']) {
+            let mut parts: Vec<String> = Vec::new();
+            for piece in line.split(',') {
+                match parts.last_mut() {
+                    Some(last) if !piece.contains(['=', ':']) => {
+                        last.push(',');
+                        last.push_str(piece);
+                    }
+                    _ => parts.push(piece.to_owned()),
+                }
+            }
+            for part in parts {
+                if let Some((k, v)) = part.split_once('=').or_else(|| part.split_once(':')) {
+                    self.apply(k, v);
+                }
+            }
+        }
+    }
+
+    /// Card size in pixels: explicit dimensions win, otherwise derived from the grid.
+    fn resolve(&self, atlas_size: [usize; 2]) -> Option<[usize; 2]> {
+        let w = self
+            .width
+            .or_else(|| self.cols.filter(|c| *c > 0).map(|c| atlas_size[0] / c))?;
+        let h = self
+            .height
+            .or_else(|| self.rows.filter(|r| *r > 0).map(|r| atlas_size[1] / r))?;
+        if w == 0 || h == 0 { None } else { Some([w, h]) }
+    }
+}
+
+// Store a parsed value; text that is not a number leaves an earlier hint in place
+fn set(slot: &mut Option<usize>, value: &str) {
+    if let Ok(v) = value.parse() {
+        *slot = Some(v);
+    }
+}
+
+// "535x752", "535×752" or "535,752"
+fn parse_pair(s: &str) -> Option<(usize, usize)> {
+    let (a, b) = s.split_once(['x', 'X', '×', ','])?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+/// Read the card size suggested by the PNG's text metadata, if any.
+/// Only the chunks before the image data are inspected, so this is cheap even for large sheets.
+/// Non-PNG input or a PNG without recognizable hints returns `None`.
+pub fn card_size_hint(reader: impl Read, atlas_size: [usize; 2]) -> Option<[usize; 2]> {
+    let reader = png::Decoder::new(reader).read_info().ok()?;
+    let info = reader.info();
+    let mut hints = Hints::default();
+    for chunk in &info.uncompressed_latin1_text {
+        hints.apply_chunk(&chunk.keyword, &chunk.text);
+    }
+    for chunk in &info.compressed_latin1_text {
+        if let Ok(text) = chunk.get_text() {
+            hints.apply_chunk(&chunk.keyword, &text);
+        }
+    }
+    for chunk in &info.utf8_text {
+        if let Ok(text) = chunk.get_text() {
+            hints.apply_chunk(&chunk.keyword, &text);
+        }
+    }
+    hints.resolve(atlas_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1×1 PNG carrying the given tEXt and iTXt chunks (keyword, text)
+    fn png_with_text(text: &[(&str, &str)], itxt: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 1, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        for (keyword, value) in text {
+            encoder
+                .add_text_chunk((*keyword).to_owned(), (*value).to_owned())
+                .expect("valid tEXt chunk");
+        }
+        for (keyword, value) in itxt {
+            encoder
+                .add_itxt_chunk((*keyword).to_owned(), (*value).to_owned())
+                .expect("valid iTXt chunk");
+        }
+        let mut writer = encoder.write_header().expect("PNG header");
+        writer.write_image_data(&[0; 4]).expect("PNG data");
+        writer.finish().expect("PNG end");
+        bytes
+    }
+
+    #[test]
+    fn one_text_chunk_per_value() {
+        let png = png_with_text(&[("card_width", "535"), ("card_height", "752")], &[]);
+        assert_eq!(
+            card_size_hint(png.as_slice(), [5350, 752]),
+            Some([535, 752]),
+            "tEXt chunks name the values"
+        );
+    }
+
+    #[test]
+    fn key_value_pairs_in_an_itxt_chunk() {
+        let png = png_with_text(&[], &[("Comment", "card_width=535; card_height: 752")]);
+        assert_eq!(
+            card_size_hint(png.as_slice(), [5350, 752]),
+            Some([535, 752]),
+            "pairs inside the text are read"
+        );
+    }
+
+    #[test]
+    fn comma_inside_a_value_is_not_a_separator() {
+        let png = png_with_text(&[], &[("Comment", "card_size=535,752")]);
+        assert_eq!(
+            card_size_hint(png.as_slice(), [5350, 752]),
+            Some([535, 752]),
+            "the pair value keeps its comma"
+        );
+        let png = png_with_text(&[("Comment", "grid=10,2, author: someone")], &[]);
+        assert_eq!(
+            card_size_hint(png.as_slice(), [5350, 1504]),
+            Some([535, 752]),
+            "the grid value keeps its comma"
+        );
+    }
+
+    #[test]
+    fn grid_dimensions_give_the_card_size() {
+        let png = png_with_text(&[("grid", "10x2")], &[]);
+        assert_eq!(
+            card_size_hint(png.as_slice(), [5350, 1504]),
+            Some([535, 752]),
+            "the atlas is split by the grid"
+        );
+    }
+
+    #[test]
+    fn unparsable_value_keeps_the_earlier_hint() {
+        let png = png_with_text(
+            &[("card_width", "535"), ("card_height", "752")],
+            &[("Comment", "card_width=unknown")],
+        );
+        assert_eq!(
+            card_size_hint(png.as_slice(), [5350, 752]),
+            Some([535, 752]),
+            "a bad value must not clear a good one"
+        );
+    }
+
+    #[test]
+    fn no_hints_or_not_a_png() {
+        let png = png_with_text(&[("Software", "paint")], &[]);
+        assert_eq!(
+            card_size_hint(png.as_slice(), [100, 100]),
+            None,
+            "unrelated text is no hint"
+        );
+        assert_eq!(
+            card_size_hint(&b"GIF89a"[..], [100, 100]),
+            None,
+            "non-PNG input is no hint"
+        );
+    }
+}